@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use axum::{
+    extract::Extension,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Router, routing::get,
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+use serde::{de::DeserializeOwned, Serialize};
+use tower_sessions::Session;
+use webauthn_rs::prelude::*;
+
+use crate::api_tokens::{ApiToken, ApiTokenResponse};
+use crate::audit::{self, AuditAction, AuditEvent, AuditEventResponse, RequestContext};
+use crate::auth::{current_user, User, UserRole};
+use crate::error::WebauthnError;
+use crate::extract::Json;
+use crate::orgs::{OrgInvite, OrgMembership, OrgRole, Organization};
+use crate::polls::{poll_response, record_close, Poll, PollResponse, PollRole, Vote};
+use crate::reauth::RecentlyVerified;
+use crate::reports::{Appeal, AppealStatus, Report, ReportReason, ReportStatus};
+use crate::rounds::{ArchivedVote, PollRound};
+use crate::sessions::UserSession;
+use crate::startup::AppState;
+use crate::webhooks::{Delivery, Webhook, WebhookResponse};
+
+/// Stands in for a deleted user wherever a record has to keep pointing at
+/// someone, e.g. the creator of an anonymised poll.
+pub const DELETED_USER_ID: ObjectId = ObjectId::from_bytes([0; 12]);
+pub const DELETED_USERNAME: &str = "[deleted]";
+
+/// What happens to a deleted user's polls and votes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountRetention {
+    /// Polls go to a co-owner if they have one and are otherwise closed and
+    /// attributed to nobody. Ballots are unlinked but still count.
+    Anonymize,
+    /// Polls are deleted along with their votes, and the user's ballots are
+    /// taken out of the tallies of other polls.
+    Delete,
+}
+
+impl FromStr for AccountRetention {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "anonymize" => Ok(AccountRetention::Anonymize),
+            "delete" => Ok(AccountRetention::Delete),
+            _ => Err("expected anonymize or delete".into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    pub exported_at: String,
+    pub profile: ProfileExport,
+    pub credentials: Vec<CredentialExport>,
+    pub polls_created: Vec<PollResponse>,
+    pub votes: Vec<VoteExport>,
+    /// Ballots from rounds that were since reset, kept so the reset can be undone.
+    pub archived_votes: Vec<ArchivedVoteExport>,
+    pub poll_roles: Vec<PollRoleExport>,
+    pub org_memberships: Vec<OrgMembershipExport>,
+    pub org_invites: Vec<OrgInviteExport>,
+    pub reports: Vec<ReportExport>,
+    pub appeals: Vec<AppealExport>,
+    pub api_tokens: Vec<ApiTokenResponse>,
+    pub sessions: Vec<SessionExport>,
+    pub webhooks: Vec<WebhookResponse>,
+    /// Audit records of what the user did or what was done to their account.
+    pub audit_events: Vec<AuditEventResponse>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileExport {
+    pub id: String,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub role: UserRole,
+    pub suspended: bool,
+    pub recovery_codes_remaining: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CredentialExport {
+    pub credential_id: CredentialID,
+    pub algorithm: COSEAlgorithm,
+    pub possibly_cloned_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoteExport {
+    pub poll_id: String,
+    pub poll_title: String,
+    pub option_id: String,
+    pub option_text: String,
+    pub voted_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchivedVoteExport {
+    pub poll_id: String,
+    pub round: i32,
+    pub option_id: String,
+    pub voted_at: String,
+}
+
+/// A role on someone else's poll.
+#[derive(Debug, Serialize)]
+pub struct PollRoleExport {
+    pub poll_id: String,
+    pub poll_title: String,
+    pub role: PollRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgMembershipExport {
+    pub org_id: String,
+    pub org_name: String,
+    pub role: OrgRole,
+    pub joined_at: String,
+}
+
+/// An invitation the user hasn't answered yet.
+#[derive(Debug, Serialize)]
+pub struct OrgInviteExport {
+    pub org_id: String,
+    pub org_name: String,
+    pub role: OrgRole,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportExport {
+    pub id: String,
+    pub poll_id: String,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppealExport {
+    pub id: String,
+    pub poll_id: String,
+    pub message: String,
+    pub status: AppealStatus,
+    pub created_at: String,
+}
+
+/// Every session on record, including ended ones.
+#[derive(Debug, Serialize)]
+pub struct SessionExport {
+    pub id: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub expires_at: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub revoked: bool,
+}
+
+async fn find_all<T>(app_state: &AppState, collection: &str, filter: Document) -> Result<Vec<T>, WebauthnError>
+where
+    T: DeserializeOwned + Unpin + Send + Sync,
+{
+    app_state.db.collection::<T>(collection).find(filter, None).await
+        .map_err(|e| { error!("Failed to fetch {}: {:?}", collection, e); WebauthnError::DatabaseError })?
+        .try_collect().await
+        .map_err(|e| { error!("Failed to collect {}: {:?}", collection, e); WebauthnError::DatabaseError })
+}
+
+/// Everything we hold about the caller, as a JSON download. Secrets are
+/// left out: token hashes, webhook signing secrets and the passkey itself.
+pub async fn export_account(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, user) = current_user(&app_state, &session).await?;
+    let user_collection = app_state.db.collection::<User>("users");
+    let poll_collection = app_state.db.collection::<Poll>("polls");
+
+    let mut polls_created = Vec::new();
+    let mut cursor = poll_collection.find(doc! { "creator_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch polls: {:?}", e); WebauthnError::DatabaseError })?;
+    while let Some(poll) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect polls: {:?}", e); WebauthnError::DatabaseError })? {
+        polls_created.push(poll_response(&user_collection, poll).await?);
+    }
+
+    let mut ballots = Vec::new();
+    let mut cursor = app_state.db.collection::<Vote>("votes").find(doc! { "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch votes: {:?}", e); WebauthnError::DatabaseError })?;
+    while let Some(vote) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect votes: {:?}", e); WebauthnError::DatabaseError })? {
+        ballots.push(vote);
+    }
+    let poll_ids: Vec<ObjectId> = ballots.iter().map(|vote| vote.poll_id).collect();
+    let mut voted_polls = HashMap::new();
+    let mut cursor = poll_collection.find(doc! { "_id": { "$in": poll_ids } }, None).await
+        .map_err(|e| { error!("Failed to fetch polls: {:?}", e); WebauthnError::DatabaseError })?;
+    while let Some(poll) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect polls: {:?}", e); WebauthnError::DatabaseError })? {
+        if let Some(id) = poll.id {
+            voted_polls.insert(id, poll);
+        }
+    }
+    let votes = ballots.into_iter().map(|vote| {
+        let poll = voted_polls.get(&vote.poll_id);
+        VoteExport {
+            poll_id: vote.poll_id.to_string(),
+            poll_title: poll.map(|poll| poll.title.clone()).unwrap_or_default(),
+            option_text: poll.and_then(|poll| poll.options.iter().find(|option| option.id == vote.option_id))
+                .map(|option| option.text.clone()).unwrap_or_default(),
+            option_id: vote.option_id,
+            voted_at: vote.voted_at.to_string(),
+        }
+    }).collect();
+
+    let poll_roles = find_all::<Poll>(&app_state, "polls", doc! { "members.user_id": &user_id }).await?
+        .into_iter()
+        .filter_map(|poll| Some(PollRoleExport {
+            role: poll.role_of(&user_id)?,
+            poll_id: poll.id?.to_string(),
+            poll_title: poll.title,
+        }))
+        .collect();
+
+    let archived_votes = find_all::<ArchivedVote>(&app_state, "archived_votes", doc! { "user_id": &user_id }).await?
+        .into_iter()
+        .map(|ballot| ArchivedVoteExport {
+            poll_id: ballot.poll_id.to_string(),
+            round: ballot.round,
+            option_id: ballot.option_id,
+            voted_at: ballot.voted_at.to_string(),
+        })
+        .collect();
+
+    let memberships = find_all::<OrgMembership>(&app_state, "org_members", doc! { "user_id": &user_id }).await?;
+    let invites = find_all::<OrgInvite>(&app_state, "org_invites", doc! { "user_id": &user_id }).await?;
+    let org_ids: Vec<ObjectId> = memberships.iter().map(|membership| membership.org_id)
+        .chain(invites.iter().map(|invite| invite.org_id))
+        .collect();
+    let org_names: HashMap<ObjectId, String> = find_all::<Organization>(&app_state, "orgs", doc! { "_id": { "$in": org_ids } }).await?
+        .into_iter()
+        .filter_map(|org| Some((org.id?, org.name)))
+        .collect();
+    let org_memberships = memberships.into_iter().map(|membership| OrgMembershipExport {
+        org_id: membership.org_id.to_string(),
+        org_name: org_names.get(&membership.org_id).cloned().unwrap_or_default(),
+        role: membership.role,
+        joined_at: membership.joined_at.to_string(),
+    }).collect();
+    let org_invites = invites.into_iter().map(|invite| OrgInviteExport {
+        org_id: invite.org_id.to_string(),
+        org_name: org_names.get(&invite.org_id).cloned().unwrap_or_default(),
+        role: invite.role,
+        created_at: invite.created_at.to_string(),
+        expires_at: invite.expires_at.to_string(),
+    }).collect();
+
+    let reports = find_all::<Report>(&app_state, "reports", doc! { "reporter_id": &user_id }).await?
+        .into_iter()
+        .map(|report| ReportExport {
+            id: report.id.map(|id| id.to_string()).unwrap_or_default(),
+            poll_id: report.poll_id.to_string(),
+            reason: report.reason,
+            details: report.details,
+            status: report.status,
+            created_at: report.created_at.to_string(),
+        })
+        .collect();
+    let appeals = find_all::<Appeal>(&app_state, "appeals", doc! { "creator_id": &user_id }).await?
+        .into_iter()
+        .map(|appeal| AppealExport {
+            id: appeal.id.map(|id| id.to_string()).unwrap_or_default(),
+            poll_id: appeal.poll_id.to_string(),
+            message: appeal.message,
+            status: appeal.status,
+            created_at: appeal.created_at.to_string(),
+        })
+        .collect();
+
+    let api_tokens = find_all::<ApiToken>(&app_state, "api_tokens", doc! { "user_id": &user_id }).await?
+        .into_iter().map(ApiTokenResponse::from).collect();
+    let sessions = find_all::<UserSession>(&app_state, "user_sessions", doc! { "user_id": &user_id }).await?
+        .into_iter()
+        .map(|entry| SessionExport {
+            id: entry.id.map(|id| id.to_string()).unwrap_or_default(),
+            created_at: entry.created_at.to_string(),
+            last_seen_at: entry.last_seen_at.to_string(),
+            expires_at: entry.expires_at.to_string(),
+            ip: entry.ip,
+            user_agent: entry.user_agent,
+            revoked: entry.revoked,
+        })
+        .collect();
+    let webhooks = find_all::<Webhook>(&app_state, "webhooks", doc! { "owner_id": &user_id }).await?
+        .into_iter().map(WebhookResponse::from).collect();
+
+    let mut events = find_all::<AuditEvent>(&app_state, "audit_events",
+        doc! { "$or": [{ "actor_id": &user_id }, { "target": user_id.to_hex() }] }).await?;
+    events.sort_by_key(|event| event.seq);
+    let audit_events = events.into_iter().map(AuditEventResponse::from).collect();
+
+    let export = AccountExport {
+        exported_at: DateTime::now().to_string(),
+        profile: ProfileExport {
+            id: user_id.to_string(),
+            username: user.username.clone(),
+            display_name: user.display_name.clone(),
+            role: user.role,
+            suspended: user.suspended,
+            recovery_codes_remaining: user.recovery_code_hashes.len(),
+        },
+        credentials: vec![CredentialExport {
+            credential_id: user.keys.cred_id().clone(),
+            algorithm: *user.keys.cred_algorithm(),
+            possibly_cloned_at: user.passkey_possibly_cloned_at.map(|at| at.to_string()),
+        }],
+        polls_created,
+        votes,
+        archived_votes,
+        poll_roles,
+        org_memberships,
+        org_invites,
+        reports,
+        appeals,
+        api_tokens,
+        sessions,
+        webhooks,
+        audit_events,
+    };
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::AccountExported, Some(user_id.to_hex())).await;
+    Ok((
+        [(header::CONTENT_DISPOSITION, "attachment; filename=\"account-export.json\"")],
+        Json(export),
+    ))
+}
+
+/// Orgs the user owns alone get in the way of deleting the account, unless
+/// nobody else is in them, in which case they go too.
+async fn release_orgs(app_state: &AppState, user_id: &ObjectId) -> Result<(), WebauthnError> {
+    let memberships = app_state.db.collection::<OrgMembership>("org_members");
+    let mut cursor = memberships.find(doc! { "user_id": user_id, "role": "owner" }, None).await
+        .map_err(|e| { error!("Failed to fetch org memberships: {:?}", e); WebauthnError::DatabaseError })?;
+    let mut owned = Vec::new();
+    while let Some(membership) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect org memberships: {:?}", e); WebauthnError::DatabaseError })? {
+        owned.push(membership.org_id);
+    }
+
+    let mut empty = Vec::new();
+    for org_id in owned {
+        let others = doc! { "org_id": &org_id, "user_id": { "$ne": user_id } };
+        if memberships.count_documents(others.clone(), None).await
+            .map_err(|e| { error!("Failed to count org members: {:?}", e); WebauthnError::DatabaseError })? == 0 {
+            empty.push(org_id);
+            continue;
+        }
+        let mut owners = others;
+        owners.insert("role", "owner");
+        if memberships.count_documents(owners, None).await
+            .map_err(|e| { error!("Failed to count org owners: {:?}", e); WebauthnError::DatabaseError })? == 0 {
+            return Err(WebauthnError::Conflict("Make someone else an owner of your organizations before deleting your account".into()));
+        }
+    }
+
+    app_state.db.collection::<OrgInvite>("org_invites")
+        .delete_many(doc! { "$or": [{ "org_id": { "$in": &empty } }, { "user_id": user_id }] }, None).await
+        .map_err(|e| { error!("Failed to delete org invites: {:?}", e); WebauthnError::DatabaseError })?;
+    memberships.delete_many(doc! { "user_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to delete org memberships: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<Organization>("orgs").delete_many(doc! { "_id": { "$in": &empty } }, None).await
+        .map_err(|e| { error!("Failed to delete orgs: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+/// Deletes a poll and everything that hangs off it: ballots, past rounds,
+/// reports and appeals, and webhooks scoped to it. The poll goes last, so
+/// that if a step fails the poll can still be found and deleted again.
+pub async fn delete_poll_data(app_state: &AppState, poll_id: &ObjectId) -> Result<(), WebauthnError> {
+    app_state.db.collection::<Vote>("votes").delete_many(doc! { "poll_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to delete votes: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<ArchivedVote>("archived_votes").delete_many(doc! { "poll_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to delete archived votes: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<PollRound>("poll_rounds").delete_many(doc! { "poll_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to delete poll rounds: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<Report>("reports").delete_many(doc! { "poll_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to delete reports: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<Appeal>("appeals").delete_many(doc! { "poll_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to delete appeals: {:?}", e); WebauthnError::DatabaseError })?;
+    delete_webhooks(app_state, doc! { "poll_id": poll_id }).await?;
+    app_state.db.collection::<Poll>("polls").delete_one(doc! { "_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to delete poll: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+/// Deletes the webhooks matching `filter` along with their delivery logs.
+async fn delete_webhooks(app_state: &AppState, filter: Document) -> Result<(), WebauthnError> {
+    let webhook_collection = app_state.db.collection::<Webhook>("webhooks");
+    let mut cursor = webhook_collection.find(filter.clone(), None).await
+        .map_err(|e| { error!("Failed to fetch webhooks: {:?}", e); WebauthnError::DatabaseError })?;
+    let mut webhook_ids = Vec::new();
+    while let Some(webhook) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect webhooks: {:?}", e); WebauthnError::DatabaseError })? {
+        webhook_ids.extend(webhook.id);
+    }
+    app_state.db.collection::<Delivery>("webhook_deliveries").delete_many(doc! { "webhook_id": { "$in": &webhook_ids } }, None).await
+        .map_err(|e| { error!("Failed to delete webhook deliveries: {:?}", e); WebauthnError::DatabaseError })?;
+    webhook_collection.delete_many(filter, None).await
+        .map_err(|e| { error!("Failed to delete webhooks: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+async fn dispose_polls(app_state: &AppState, user_id: &ObjectId, retention: AccountRetention) -> Result<(), WebauthnError> {
+    let poll_collection = app_state.db.collection::<Poll>("polls");
+    let mut cursor = poll_collection.find(doc! { "creator_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch polls: {:?}", e); WebauthnError::DatabaseError })?;
+    let mut polls = Vec::new();
+    while let Some(poll) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect polls: {:?}", e); WebauthnError::DatabaseError })? {
+        polls.push(poll);
+    }
+
+    for poll in polls {
+        let Some(poll_id) = poll.id else { continue };
+        if retention == AccountRetention::Delete {
+            delete_poll_data(app_state, &poll_id).await?;
+            continue;
+        }
+        let heir = poll.members.iter().find(|member| member.role == PollRole::Owner).map(|member| member.user_id);
+        match heir {
+            Some(heir) => {
+                poll_collection.update_one(
+                    doc! { "_id": &poll_id },
+                    doc! { "$set": { "creator_id": heir }, "$pull": { "members": { "user_id": heir } } },
+                    None,
+                ).await.map_err(|e| { error!("Failed to reassign poll: {:?}", e); WebauthnError::DatabaseError })?;
+            }
+            None => {
+                if poll.accepting_votes() {
+                    record_close(app_state, &poll_id, &poll, Some(*user_id)).await?;
+                }
+                poll_collection.update_one(doc! { "_id": &poll_id }, doc! { "$set": { "creator_id": DELETED_USER_ID } }, None).await
+                    .map_err(|e| { error!("Failed to anonymise poll: {:?}", e); WebauthnError::DatabaseError })?;
+            }
+        }
+    }
+
+    // Roles the user holds on other people's polls.
+    poll_collection.update_many(doc! { "members.user_id": user_id }, doc! { "$pull": { "members": { "user_id": user_id } } }, None).await
+        .map_err(|e| { error!("Failed to remove poll roles: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+async fn dispose_votes(app_state: &AppState, user_id: &ObjectId, retention: AccountRetention) -> Result<(), WebauthnError> {
+    let vote_collection = app_state.db.collection::<Vote>("votes");
+    if retention == AccountRetention::Delete {
+        let mut cursor = vote_collection.find(doc! { "user_id": user_id }, None).await
+            .map_err(|e| { error!("Failed to fetch votes: {:?}", e); WebauthnError::DatabaseError })?;
+        while let Some(vote) = cursor.try_next().await
+            .map_err(|e| { error!("Failed to collect votes: {:?}", e); WebauthnError::DatabaseError })? {
+            // The ballot goes first, so that a retry after a failure here
+            // can't take the same vote out of the tally twice.
+            let Some(vote_id) = vote.id else { continue };
+            let deleted = vote_collection.delete_one(doc! { "_id": vote_id }, None).await
+                .map_err(|e| { error!("Failed to delete vote: {:?}", e); WebauthnError::DatabaseError })?;
+            if deleted.deleted_count == 0 {
+                continue;
+            }
+            app_state.db.collection::<Poll>("polls").update_one(
+                doc! { "_id": &vote.poll_id, "options.id": &vote.option_id },
+                doc! { "$inc": { "options.$.votes": -1, "total_votes": -1 } },
+                None,
+            ).await.map_err(|e| { error!("Failed to retract vote: {:?}", e); WebauthnError::DatabaseError })?;
+        }
+    }
+    // Tallies live on the poll, so dropping the ballot is what unlinks it.
+    vote_collection.delete_many(doc! { "user_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to delete votes: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<ArchivedVote>("archived_votes").delete_many(doc! { "user_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to delete archived votes: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+/// Deletes the caller's account. Polls and votes are handled according to
+/// `ACCOUNT_DELETION_RETENTION`; tokens, webhooks, sessions and memberships
+/// are removed. The audit log is append-only and keeps its records.
+///
+/// The steps aren't one transaction, but each is safe to repeat and the
+/// user record goes last, so if one fails the account is still there and
+/// the request can simply be retried.
+pub async fn delete_account(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    _verified: RecentlyVerified,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, _) = current_user(&app_state, &session).await?;
+    let retention = app_state.config.account_retention;
+
+    // Checked first, since it is the one step that can refuse.
+    release_orgs(&app_state, &user_id).await?;
+    dispose_polls(&app_state, &user_id, retention).await?;
+    dispose_votes(&app_state, &user_id, retention).await?;
+
+    app_state.db.collection::<Report>("reports")
+        .update_many(doc! { "reporter_id": &user_id }, doc! { "$set": { "reporter_id": DELETED_USER_ID } }, None).await
+        .map_err(|e| { error!("Failed to anonymise reports: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<Appeal>("appeals")
+        .update_many(doc! { "creator_id": &user_id }, doc! { "$set": { "creator_id": DELETED_USER_ID } }, None).await
+        .map_err(|e| { error!("Failed to anonymise appeals: {:?}", e); WebauthnError::DatabaseError })?;
+
+    delete_webhooks(&app_state, doc! { "owner_id": &user_id }).await?;
+    app_state.db.collection::<ApiToken>("api_tokens").delete_many(doc! { "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to delete API tokens: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<UserSession>("user_sessions").delete_many(doc! { "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to delete sessions: {:?}", e); WebauthnError::DatabaseError })?;
+
+    app_state.db.collection::<User>("users").delete_one(doc! { "_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to delete user: {:?}", e); WebauthnError::DatabaseError })?;
+    let _ = session.flush().await;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::AccountDeleted, Some(user_id.to_hex())).await;
+    info!("User {} deleted their account ({:?})", user_id, retention);
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/account", axum::routing::delete(delete_account))
+        .route("/api/account/export", get(export_account))
+}
@@ -0,0 +1,259 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{delete, get, post},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime, Document};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+use crate::account;
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::{current_user, User, UserRole};
+use crate::error::WebauthnError;
+use crate::extract::{parse_id, Json, Path, Query};
+use crate::polls::{record_close, Poll};
+use crate::profiles::is_duplicate_key;
+use crate::reauth;
+use crate::sessions;
+use crate::startup::AppState;
+
+/// `_id` of the `server_state` document recording that the bootstrap token
+/// has been used.
+const ADMIN_BOOTSTRAP_MARKER: &str = "admin_bootstrap";
+
+#[derive(Debug, Deserialize)]
+pub struct UserQueryParams {
+    suspended: Option<bool>,
+    role: Option<UserRole>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BootstrapRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetRoleRequest {
+    pub role: UserRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub role: UserRole,
+    pub suspended: bool,
+    pub passkey_possibly_cloned_at: Option<String>,
+}
+
+/// Fails with `Forbidden` unless the session belongs to an admin. Returns the admin's ID.
+pub async fn require_admin(app_state: &AppState, session: &Session) -> Result<ObjectId, WebauthnError> {
+    let (user_id, user) = current_user(app_state, session).await?;
+    if user.role != UserRole::Admin {
+        info!("User {} is not an admin", user_id);
+        return Err(WebauthnError::Forbidden);
+    }
+    Ok(user_id)
+}
+
+pub async fn list_users(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<UserQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    require_admin(&app_state, &session).await?;
+
+    let mut filter = doc! {};
+    if let Some(suspended) = params.suspended {
+        // Older user documents have no `suspended` field at all.
+        filter.insert("suspended", if suspended { doc! { "$eq": true } } else { doc! { "$ne": true } });
+    }
+    if let Some(role) = params.role {
+        filter.insert("role", if role == UserRole::Admin { doc! { "$eq": "admin" } } else { doc! { "$ne": "admin" } });
+    }
+
+    let mut cursor = app_state.db.collection::<User>("users").find(filter, None).await
+        .map_err(|e| { error!("Failed to fetch users: {:?}", e); WebauthnError::DatabaseError })?;
+    let mut users = Vec::new();
+    while let Some(user) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect users: {:?}", e); WebauthnError::DatabaseError })? {
+        users.push(AdminUserResponse {
+            id: user.id.map(|id| id.to_string()).unwrap_or_default(),
+            username: user.username,
+            display_name: user.display_name,
+            role: user.role,
+            suspended: user.suspended,
+            passkey_possibly_cloned_at: user.passkey_possibly_cloned_at.map(|at| at.to_string()),
+        });
+    }
+    Ok(Json(users))
+}
+
+async fn set_user_field(app_state: &AppState, user_id: &ObjectId, update: mongodb::bson::Document) -> Result<(), WebauthnError> {
+    let result = app_state.db.collection::<User>("users")
+        .update_one(doc! { "_id": user_id }, doc! { "$set": update }, None).await
+        .map_err(|e| { error!("Failed to update user: {:?}", e); WebauthnError::DatabaseError })?;
+    if result.matched_count == 0 {
+        return Err(WebauthnError::UserNotFound);
+    }
+    Ok(())
+}
+
+pub async fn suspend_user(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let user_id = parse_id(&params, "userId", "user")?;
+    if user_id == admin_id {
+        return Err(WebauthnError::Conflict("Admins cannot suspend themselves".into()));
+    }
+
+    set_user_field(&app_state, &user_id, doc! { "suspended": true }).await?;
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::UserSuspended, Some(user_id.to_hex())).await;
+    info!("User {} suspended by admin {}", user_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn unsuspend_user(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let user_id = parse_id(&params, "userId", "user")?;
+
+    set_user_field(&app_state, &user_id, doc! { "suspended": false }).await?;
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::UserUnsuspended, Some(user_id.to_hex())).await;
+    info!("User {} unsuspended by admin {}", user_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn set_user_role(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+    Json(req): Json<SetRoleRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let user_id = parse_id(&params, "userId", "user")?;
+    if user_id == admin_id && req.role != UserRole::Admin {
+        return Err(WebauthnError::Conflict("Admins cannot demote themselves".into()));
+    }
+
+    let role = to_bson(&req.role).map_err(|_| WebauthnError::Unknown)?;
+    set_user_field(&app_state, &user_id, doc! { "role": role }).await?;
+    // Existing sessions were granted under the old role; make the user log in again.
+    sessions::revoke_all(&app_state, &user_id).await?;
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::UserRoleChanged, Some(user_id.to_hex())).await;
+    info!("User {} given role {:?} by admin {}", user_id, req.role, admin_id);
+    Ok(StatusCode::OK)
+}
+
+/// Makes the caller the first admin, given the `ADMIN_BOOTSTRAP_TOKEN` the
+/// server was started with. It works once, and only while there is no
+/// admin; after that, admins appoint each other with `set_user_role`.
+pub async fn bootstrap_admin(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Json(req): Json<BootstrapRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, _) = current_user(&app_state, &session).await?;
+    reauth::require_recent(&app_state, &session).await?;
+    let Some(token) = &app_state.config.admin_bootstrap_token else {
+        info!("User {} tried to bootstrap an admin, but no token is configured", user_id);
+        return Err(WebauthnError::Forbidden);
+    };
+    // Comparing digests, the timing says nothing about the token.
+    if Sha256::digest(req.token.as_bytes()) != Sha256::digest(token.as_bytes()) {
+        info!("User {} gave a wrong admin bootstrap token", user_id);
+        return Err(WebauthnError::Forbidden);
+    }
+
+    let users = app_state.db.collection::<User>("users");
+    let role = to_bson(&UserRole::Admin).map_err(|_| WebauthnError::Unknown)?;
+    let admins = users.count_documents(doc! { "role": &role }, None).await
+        .map_err(|e| { error!("Failed to count admins: {:?}", e); WebauthnError::DatabaseError })?;
+    if admins > 0 {
+        return Err(WebauthnError::Conflict("An admin has already been appointed".into()));
+    }
+    // The marker's fixed ID makes the token single-use, even under a race.
+    app_state.db.collection::<Document>("server_state")
+        .insert_one(doc! { "_id": ADMIN_BOOTSTRAP_MARKER, "user_id": &user_id, "at": DateTime::now() }, None).await
+        .map_err(|e| {
+            if is_duplicate_key(&e) {
+                return WebauthnError::Conflict("The admin bootstrap token has already been used".into());
+            }
+            error!("Failed to record admin bootstrap: {:?}", e);
+            WebauthnError::DatabaseError
+        })?;
+
+    set_user_field(&app_state, &user_id, doc! { "role": role }).await?;
+    // The session now carries admin rights; an ID seen before must not.
+    session.cycle_id().await
+        .map_err(|e| { error!("Failed to cycle session ID: {:?}", e); WebauthnError::CorruptSession })?;
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::UserRoleChanged, Some(user_id.to_hex())).await;
+    info!("User {} became the first admin", user_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn force_close_poll(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let poll_id = parse_id(&params, "pollId", "poll")?;
+
+    let poll = app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": &poll_id }, None).await
+        .map_err(|e| { error!("Failed to fetch poll: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::PollNotFound)?;
+    record_close(&app_state, &poll_id, &poll, Some(admin_id)).await?;
+
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::PollClosed, Some(poll_id.to_hex())).await;
+    info!("Poll {} force-closed by admin {}", poll_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_poll(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    reauth::require_recent(&app_state, &session).await?;
+    let poll_id = parse_id(&params, "pollId", "poll")?;
+
+    app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": &poll_id }, None).await
+        .map_err(|e| { error!("Failed to fetch poll: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::PollNotFound)?;
+    account::delete_poll_data(&app_state, &poll_id).await?;
+
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::PollDeleted, Some(poll_id.to_hex())).await;
+    info!("Poll {} deleted by admin {}", poll_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/admin/bootstrap", post(bootstrap_admin))
+        .route("/api/admin/users", get(list_users))
+        .route("/api/admin/users/:userId/suspend", post(suspend_user))
+        .route("/api/admin/users/:userId/unsuspend", post(unsuspend_user))
+        .route("/api/admin/users/:userId/role", post(set_user_role))
+        .route("/api/admin/polls/:pollId/close", post(force_close_poll))
+        .route("/api/admin/polls/:pollId", delete(delete_poll))
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Extension, Request},
+    http::{header::AUTHORIZATION, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Router, routing::{delete, get},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tower_sessions::Session;
+
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::is_authenticated;
+use crate::error::{FieldError, WebauthnError};
+use crate::extract::{parse_id, Json, Path};
+use crate::startup::AppState;
+
+const TOKEN_PREFIX: &str = "pat_";
+/// Characters of the token kept in clear so users can tell tokens apart.
+const DISPLAY_PREFIX_LEN: usize = 12;
+const DEFAULT_EXPIRY_DAYS: i64 = 30;
+const MAX_EXPIRY_DAYS: i64 = 365;
+const MAX_TOKENS_PER_USER: u64 = 20;
+/// Tokens only open up the poll API; account, admin, token and webhook
+/// management stay behind a passkey login.
+const TOKEN_PATH_PREFIX: &str = "/api/polls";
+/// Poll sub-routes that change who controls a poll or wipe its votes. A
+/// leaked token shouldn't be able to hand a poll over or erase it, so
+/// these need a session whatever the token's scopes; reads are fine.
+const SESSION_ONLY_ACTIONS: &[&str] = &["members", "transfer", "reset"];
+
+tokio::task_local! {
+    static TOKEN_USER: ObjectId;
+}
+
+/// The user a bearer token on this request belongs to, if one was presented
+/// and accepted by `bearer_auth`.
+pub fn current_user_id() -> Option<ObjectId> {
+    TOKEN_USER.try_with(|user_id| *user_id).ok()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TokenScope {
+    /// GET requests under /api/polls.
+    #[serde(rename = "polls:read")]
+    PollsRead,
+    /// Everything else under /api/polls: creating, voting, closing... but
+    /// not managing roles, transferring or resetting.
+    #[serde(rename = "polls:write")]
+    PollsWrite,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub name: String,
+    /// SHA-256 of the full token; the token itself is never stored.
+    pub token_hash: String,
+    pub display_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+    pub last_used_at: Option<DateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+    #[serde(default)]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiTokenResponse {
+    pub id: String,
+    pub name: String,
+    pub display_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub last_used_at: Option<String>,
+    /// Only present in the response to creating the token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl From<ApiToken> for ApiTokenResponse {
+    fn from(token: ApiToken) -> Self {
+        ApiTokenResponse {
+            id: token.id.map(|id| id.to_string()).unwrap_or_default(),
+            name: token.name,
+            display_prefix: token.display_prefix,
+            scopes: token.scopes,
+            created_at: token.created_at.to_string(),
+            expires_at: token.expires_at.to_string(),
+            last_used_at: token.last_used_at.map(|at| at.to_string()),
+            token: None,
+        }
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// The scope a token needs for a request, or `None` if no token will do.
+fn required_scope(method: &Method, path: &str) -> Option<TokenScope> {
+    let rest = path.strip_prefix(TOKEN_PATH_PREFIX)?;
+    if !(rest.is_empty() || rest.starts_with('/')) {
+        return None;
+    }
+    if method == Method::GET || method == Method::HEAD {
+        return Some(TokenScope::PollsRead);
+    }
+    // `/api/polls/:pollId/<action>/...`
+    let action = rest.split('/').nth(2);
+    if action.is_some_and(|action| SESSION_ONLY_ACTIONS.contains(&action)) {
+        return None;
+    }
+    Some(TokenScope::PollsWrite)
+}
+
+/// Checks a bearer token against the request it came with and returns its user.
+async fn authenticate(app_state: &AppState, token: &str, method: &Method, path: &str) -> Result<ObjectId, WebauthnError> {
+    let collection = app_state.db.collection::<ApiToken>("api_tokens");
+    let api_token = collection.find_one(doc! { "token_hash": hash_token(token) }, None).await
+        .map_err(|e| { error!("Failed to fetch API token: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or_else(|| { info!("Unknown API token presented"); WebauthnError::Unauthenticated })?;
+    if api_token.expires_at <= DateTime::now() {
+        info!("Expired API token {:?} presented", api_token.id);
+        return Err(WebauthnError::Unauthenticated);
+    }
+
+    if !required_scope(method, path).is_some_and(|required| api_token.scopes.contains(&required)) {
+        info!("API token {:?} may not {} {}", api_token.id, method, path);
+        return Err(WebauthnError::Forbidden);
+    }
+
+    if let Err(e) = collection.update_one(doc! { "_id": &api_token.id }, doc! { "$set": { "last_used_at": DateTime::now() } }, None).await {
+        warn!("Failed to record API token use: {:?}", e);
+    }
+    Ok(api_token.user_id)
+}
+
+/// Accepts `Authorization: Bearer <token>` in place of a session cookie.
+/// A request with a bearer token is authenticated by the token alone; one
+/// with a bad token is rejected outright rather than falling back to the
+/// session.
+pub async fn bearer_auth(Extension(app_state): Extension<AppState>, req: Request, next: Next) -> Response {
+    let Some(header) = req.headers().get(AUTHORIZATION) else {
+        return next.run(req).await;
+    };
+    let Some(token) = header.to_str().ok().and_then(|value| value.strip_prefix("Bearer ")).map(str::trim) else {
+        return WebauthnError::Unauthenticated.into_response();
+    };
+    match authenticate(&app_state, token, req.method(), req.uri().path()).await {
+        Ok(user_id) => TOKEN_USER.scope(user_id, next.run(req)).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+pub async fn list_tokens(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let mut cursor = app_state.db.collection::<ApiToken>("api_tokens").find(doc! { "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch API tokens: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut tokens = Vec::new();
+    while let Some(token) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect API tokens: {:?}", e); WebauthnError::DatabaseError })? {
+        tokens.push(ApiTokenResponse::from(token));
+    }
+    Ok(Json(tokens))
+}
+
+/// Mints a token. The token is only ever returned here; afterwards the
+/// server knows just its hash.
+pub async fn create_token(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Json(req): Json<CreateTokenRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+
+    let name = req.name.trim().to_string();
+    let expires_in_days = req.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+    let mut field_errors = Vec::new();
+    if name.is_empty() {
+        field_errors.push(FieldError::new("name", "Name cannot be empty"));
+    }
+    if req.scopes.is_empty() {
+        field_errors.push(FieldError::new("scopes", "Choose at least one scope"));
+    }
+    if !(1..=MAX_EXPIRY_DAYS).contains(&expires_in_days) {
+        field_errors.push(FieldError::new("expires_in_days", format!("Must be between 1 and {} days", MAX_EXPIRY_DAYS)));
+    }
+    if !field_errors.is_empty() {
+        return Err(WebauthnError::InvalidFields { message: "Token is invalid".into(), fields: field_errors });
+    }
+
+    let collection = app_state.db.collection::<ApiToken>("api_tokens");
+    let existing = collection.count_documents(doc! { "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to count API tokens: {:?}", e); WebauthnError::DatabaseError })?;
+    if existing >= MAX_TOKENS_PER_USER {
+        return Err(WebauthnError::Conflict(format!("You can have at most {} tokens", MAX_TOKENS_PER_USER)));
+    }
+
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let token = format!("{}{}", TOKEN_PREFIX, hex::encode(secret));
+    let mut scopes = Vec::new();
+    for scope in req.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let now = DateTime::now();
+    let mut api_token = ApiToken {
+        id: None,
+        user_id,
+        name,
+        token_hash: hash_token(&token),
+        display_prefix: token[..DISPLAY_PREFIX_LEN].to_string(),
+        scopes,
+        created_at: now,
+        expires_at: DateTime::from_millis(now.timestamp_millis() + expires_in_days * 24 * 60 * 60 * 1000),
+        last_used_at: None,
+    };
+    let result = collection.insert_one(&api_token, None).await
+        .map_err(|e| { error!("Failed to insert API token: {:?}", e); WebauthnError::DatabaseError })?;
+    api_token.id = result.inserted_id.as_object_id();
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::ApiTokenCreated, api_token.id.map(|id| id.to_hex())).await;
+    Ok((StatusCode::CREATED, Json(ApiTokenResponse { token: Some(token), ..ApiTokenResponse::from(api_token) })))
+}
+
+pub async fn revoke_token(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let token_id = parse_id(&params, "tokenId", "token")?;
+
+    let result = app_state.db.collection::<ApiToken>("api_tokens")
+        .delete_one(doc! { "_id": &token_id, "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to delete API token: {:?}", e); WebauthnError::DatabaseError })?;
+    if result.deleted_count == 0 {
+        return Err(WebauthnError::TokenNotFound);
+    }
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::ApiTokenRevoked, Some(token_id.to_hex())).await;
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/tokens", get(list_tokens).post(create_token))
+        .route("/api/tokens/:tokenId", delete(revoke_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_need_polls_read() {
+        assert_eq!(required_scope(&Method::GET, "/api/polls"), Some(TokenScope::PollsRead));
+        assert_eq!(required_scope(&Method::GET, "/api/polls/abc/members"), Some(TokenScope::PollsRead));
+        assert_eq!(required_scope(&Method::GET, "/api/polls/abc/rounds"), Some(TokenScope::PollsRead));
+    }
+
+    #[test]
+    fn writes_need_polls_write() {
+        assert_eq!(required_scope(&Method::POST, "/api/polls"), Some(TokenScope::PollsWrite));
+        assert_eq!(required_scope(&Method::POST, "/api/polls/abc/vote"), Some(TokenScope::PollsWrite));
+        assert_eq!(required_scope(&Method::POST, "/api/polls/abc/close"), Some(TokenScope::PollsWrite));
+    }
+
+    #[test]
+    fn control_of_a_poll_needs_a_session() {
+        assert_eq!(required_scope(&Method::POST, "/api/polls/abc/members"), None);
+        assert_eq!(required_scope(&Method::DELETE, "/api/polls/abc/members/def"), None);
+        assert_eq!(required_scope(&Method::POST, "/api/polls/abc/transfer"), None);
+        assert_eq!(required_scope(&Method::POST, "/api/polls/abc/reset"), None);
+        assert_eq!(required_scope(&Method::POST, "/api/polls/abc/reset/undo"), None);
+    }
+
+    #[test]
+    fn other_apis_need_a_session() {
+        assert_eq!(required_scope(&Method::POST, "/api/webhooks"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/webhooks"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/pollsters"), None);
+        assert_eq!(required_scope(&Method::DELETE, "/api/account"), None);
+    }
+}
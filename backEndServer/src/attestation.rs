@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::str::FromStr;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use webauthn_rs::prelude::*;
+use webauthn_rs_proto::ResidentKeyRequirement;
+
+use crate::config::Config;
+
+/// Whether new passkeys have to prove what authenticator made them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationMode {
+    /// Any passkey is accepted, synced ones included.
+    None,
+    /// Only device-bound keys attested by a trusted root are accepted.
+    Required,
+}
+
+impl FromStr for AttestationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "none" => Ok(AttestationMode::None),
+            "required" => Ok(AttestationMode::Required),
+            _ => Err("expected none or required".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResidentKeyPolicy {
+    Discouraged,
+    Preferred,
+    Required,
+}
+
+impl FromStr for ResidentKeyPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "discouraged" => Ok(ResidentKeyPolicy::Discouraged),
+            "preferred" => Ok(ResidentKeyPolicy::Preferred),
+            "required" => Ok(ResidentKeyPolicy::Required),
+            _ => Err("expected discouraged, preferred or required".into()),
+        }
+    }
+}
+
+impl From<ResidentKeyPolicy> for ResidentKeyRequirement {
+    fn from(policy: ResidentKeyPolicy) -> Self {
+        match policy {
+            ResidentKeyPolicy::Discouraged => ResidentKeyRequirement::Discouraged,
+            ResidentKeyPolicy::Preferred => ResidentKeyRequirement::Preferred,
+            ResidentKeyPolicy::Required => ResidentKeyRequirement::Required,
+        }
+    }
+}
+
+/// The parts of a FIDO Metadata Service BLOB payload that we read.
+#[derive(Debug, Deserialize)]
+struct MetadataBlob {
+    entries: Vec<MetadataEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataEntry {
+    #[serde(rename = "metadataStatement")]
+    statement: Option<MetadataStatement>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetadataStatement {
+    /// Only FIDO2 authenticators have one; U2F entries are skipped.
+    aaguid: Option<Uuid>,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    attestation_root_certificates: Vec<String>,
+}
+
+/// Reads the trusted roots out of a FIDO Metadata Service BLOB. The file may
+/// be the BLOB as downloaded (a JWT) or its decoded JSON payload. The JWT
+/// signature is not checked: the file is trusted as the operator put it
+/// there. With a non-empty `aaguids`, only those authenticators are kept.
+fn load_metadata(path: &str, aaguids: &[Uuid]) -> Result<AttestationCaList, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let contents = contents.trim();
+    let payload = if contents.starts_with('{') {
+        contents.as_bytes().to_vec()
+    } else {
+        let encoded = contents.split('.').nth(1).ok_or("not a JSON document or a JWT")?;
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(encoded.trim_end_matches('='))
+            .map_err(|e| format!("JWT payload is not base64: {}", e))?
+    };
+    let blob: MetadataBlob = serde_json::from_slice(&payload).map_err(|e| format!("not a metadata BLOB: {}", e))?;
+
+    let mut builder = AttestationCaListBuilder::new();
+    for statement in blob.entries.into_iter().filter_map(|entry| entry.statement) {
+        let Some(aaguid) = statement.aaguid else { continue };
+        if !aaguids.is_empty() && !aaguids.contains(&aaguid) {
+            continue;
+        }
+        for root in &statement.attestation_root_certificates {
+            let der = base64::engine::general_purpose::STANDARD.decode(root)
+                .map_err(|e| format!("bad root certificate for {}: {}", aaguid, e))?;
+            builder.insert_device_der(&der, aaguid, statement.description.clone(), BTreeMap::new())
+                .map_err(|e| format!("bad root certificate for {}: {:?}", aaguid, e))?;
+        }
+    }
+
+    let list = builder.build();
+    if list.is_empty() {
+        return Err("no trusted authenticators left after filtering".into());
+    }
+    Ok(list)
+}
+
+/// How new passkeys are registered, from `ATTESTATION`, `RESIDENT_KEY` and
+/// friends. User verification needs no setting: webauthn-rs requires it in
+/// every passkey ceremony.
+pub struct AuthenticatorPolicy {
+    /// Set when attestation is required.
+    trusted: Option<AttestationCaList>,
+    resident_key: ResidentKeyPolicy,
+}
+
+impl AuthenticatorPolicy {
+    /// Panics on a bad configuration, so the server doesn't start with a
+    /// weaker policy than intended.
+    pub fn from_config(config: &Config) -> Self {
+        let trusted = match config.attestation {
+            AttestationMode::None => None,
+            AttestationMode::Required => {
+                let path = config.attestation_metadata_path.as_deref()
+                    .expect("ATTESTATION_METADATA_PATH must be set when ATTESTATION=required");
+                let aaguids: Vec<Uuid> = config.attestation_trusted_aaguids.iter()
+                    .map(|aaguid| aaguid.parse().unwrap_or_else(|_| panic!("{} in ATTESTATION_TRUSTED_AAGUIDS is not an AAGUID", aaguid)))
+                    .collect();
+                let list = load_metadata(path, &aaguids).unwrap_or_else(|e| panic!("Failed to load attestation metadata: {}", e));
+                info!("Requiring attestation from {} trusted roots", list.len());
+                Some(list)
+            }
+        };
+        AuthenticatorPolicy { trusted, resident_key: config.resident_key }
+    }
+
+    pub fn start_registration(&self, webauthn: &Webauthn, user_unique_id: Uuid, username: &str, display_name: &str)
+        -> WebauthnResult<(CreationChallengeResponse, RegistrationCeremony)>
+    {
+        let (mut ccr, ceremony) = match &self.trusted {
+            None => webauthn.start_passkey_registration(user_unique_id, username, display_name, None)
+                .map(|(ccr, state)| (ccr, RegistrationCeremony::Passkey(state)))?,
+            Some(trusted) => webauthn.start_attested_passkey_registration(user_unique_id, username, display_name, None, trusted.clone(), None)
+                .map(|(ccr, state)| (ccr, RegistrationCeremony::Attested(state)))?,
+        };
+        // Only a hint to the client: the server can't tell whether the key it
+        // gets back is discoverable.
+        if let Some(selection) = ccr.public_key.authenticator_selection.as_mut() {
+            selection.resident_key = Some(self.resident_key.into());
+            selection.require_resident_key = self.resident_key == ResidentKeyPolicy::Required;
+        }
+        Ok((ccr, ceremony))
+    }
+}
+
+/// Registration state kept in the session between start and finish.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RegistrationCeremony {
+    Passkey(PasskeyRegistration),
+    Attested(AttestedPasskeyRegistration),
+}
+
+impl RegistrationCeremony {
+    /// Attested passkeys are stored like any other once their attestation
+    /// has been checked.
+    pub fn finish(&self, webauthn: &Webauthn, reg: &RegisterPublicKeyCredential) -> WebauthnResult<Passkey> {
+        match self {
+            RegistrationCeremony::Passkey(state) => webauthn.finish_passkey_registration(reg, state),
+            RegistrationCeremony::Attested(state) => webauthn.finish_attested_passkey_registration(reg, state).map(Passkey::from),
+        }
+    }
+}
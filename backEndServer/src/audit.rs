@@ -0,0 +1,564 @@
+use std::net::SocketAddr;
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, Extension, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+    response::IntoResponse,
+    Router, routing::get,
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime};
+use mongodb::options::{FindOneOptions, FindOptions};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use tower_sessions::Session;
+
+use crate::admin::require_admin;
+use crate::auth::is_authenticated;
+use crate::error::WebauthnError;
+use crate::extract::{Json, Query};
+use crate::startup::AppState;
+
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const DEFAULT_QUERY_LIMIT: i64 = 100;
+const MAX_QUERY_LIMIT: i64 = 1000;
+const ACTIVITY_LIMIT: i64 = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    UserRegistered,
+    LoginSucceeded,
+    LoginFailed,
+    Logout,
+    SessionRevoked,
+    AllSessionsRevoked,
+    Reauthenticated,
+    ReauthenticationFailed,
+    RecoveryCodesGenerated,
+    RecoveryCodeUsed,
+    PasskeyRecovered,
+    PasskeyPossiblyCloned,
+    UsernameChanged,
+    AccountExported,
+    AccountDeleted,
+    UserSuspended,
+    UserUnsuspended,
+    UserRoleChanged,
+    ApiTokenCreated,
+    ApiTokenRevoked,
+    PollCreated,
+    PollClosed,
+    PollReopened,
+    PollDeadlineChanged,
+    PollReset,
+    PollResetUndone,
+    PollDeleted,
+    ReportResolved,
+    ReportDismissed,
+    AppealAccepted,
+    AppealRejected,
+}
+
+impl AuditAction {
+    /// Actions shown to users in their "recent security activity" view.
+    const SECURITY: &'static [AuditAction] = &[
+        AuditAction::UserRegistered,
+        AuditAction::LoginSucceeded,
+        AuditAction::LoginFailed,
+        AuditAction::Logout,
+        AuditAction::SessionRevoked,
+        AuditAction::AllSessionsRevoked,
+        AuditAction::Reauthenticated,
+        AuditAction::ReauthenticationFailed,
+        AuditAction::RecoveryCodesGenerated,
+        AuditAction::RecoveryCodeUsed,
+        AuditAction::PasskeyRecovered,
+        AuditAction::PasskeyPossiblyCloned,
+        AuditAction::UsernameChanged,
+        AuditAction::AccountExported,
+        AuditAction::UserSuspended,
+        AuditAction::UserUnsuspended,
+        AuditAction::UserRoleChanged,
+        AuditAction::ApiTokenCreated,
+        AuditAction::ApiTokenRevoked,
+    ];
+}
+
+/// One record in the append-only audit chain. `hash` covers every other
+/// field plus `prev_hash`, so editing or deleting a record breaks the chain
+/// from that point on.
+///
+/// With `AUDIT_HMAC_KEY` set, `hash` is an HMAC, so that someone who can
+/// write to the database still can't forge a consistent chain. Records
+/// from before the key was set are plain SHA-256 and stay verifiable, but
+/// only up to `AUDIT_KEYED_SINCE`: otherwise a forger could rewrite the
+/// whole chain unkeyed.
+/// Neither stops the newest records being cut off; for that, each append
+/// logs the new head, and `verify_audit_log` checks against a head noted
+/// from there or remembered since startup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEvent {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub seq: i64,
+    pub at: DateTime,
+    pub actor_id: Option<ObjectId>,
+    pub action: AuditAction,
+    pub target: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+    /// Whether `hash` is an HMAC under `AUDIT_HMAC_KEY`.
+    #[serde(default)]
+    pub keyed: bool,
+}
+
+/// The newest record of the chain, as noted outside the database.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ChainHead {
+    pub seq: i64,
+    pub hash: String,
+}
+
+impl AuditEvent {
+    /// `None` when the record is keyed but there is no key to check it with.
+    fn compute_hash(&self, key: Option<&str>) -> Option<String> {
+        let action = to_bson(&self.action).ok().and_then(|a| a.as_str().map(str::to_string)).unwrap_or_default();
+        let fields = [
+            self.prev_hash.clone(),
+            self.seq.to_string(),
+            self.at.timestamp_millis().to_string(),
+            self.actor_id.map(|id| id.to_hex()).unwrap_or_default(),
+            action,
+            self.target.clone().unwrap_or_default(),
+            self.ip.clone().unwrap_or_default(),
+            self.user_agent.clone().unwrap_or_default(),
+        ];
+        if self.keyed {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key?.as_bytes()).expect("HMAC accepts keys of any length");
+            for field in &fields {
+                mac.update(&field.len().to_be_bytes());
+                mac.update(field.as_bytes());
+            }
+            Some(hex::encode(mac.finalize().into_bytes()))
+        } else {
+            let mut hasher = Sha256::new();
+            for field in &fields {
+                hasher.update(field.len().to_be_bytes());
+                hasher.update(field.as_bytes());
+            }
+            Some(hex::encode(hasher.finalize()))
+        }
+    }
+}
+
+/// Checks records one by one, in `seq` order.
+struct ChainVerifier<'a> {
+    key: Option<&'a str>,
+    /// With a key, records from this `seq` on must be keyed.
+    keyed_since: i64,
+    expected_seq: i64,
+    expected_prev: String,
+    seen_keyed: bool,
+    head: Option<ChainHead>,
+    checked: i64,
+}
+
+impl<'a> ChainVerifier<'a> {
+    fn new(key: Option<&'a str>, keyed_since: i64) -> Self {
+        ChainVerifier { key, keyed_since, expected_seq: 1, expected_prev: GENESIS_HASH.to_string(), seen_keyed: false, head: None, checked: 0 }
+    }
+
+    /// Fails with the record's `seq` if it doesn't follow on from the last.
+    fn check(&mut self, event: AuditEvent) -> Result<(), i64> {
+        // Once keyed, always keyed: an unkeyed record after a keyed one, or
+        // past the legacy prefix, could have been written by anyone.
+        let must_be_keyed = self.seen_keyed || (self.key.is_some() && event.seq >= self.keyed_since);
+        let downgraded = must_be_keyed && !event.keyed;
+        if downgraded || event.seq != self.expected_seq || event.prev_hash != self.expected_prev
+            || event.compute_hash(self.key).as_ref() != Some(&event.hash) {
+            return Err(event.seq);
+        }
+        self.seen_keyed |= event.keyed;
+        self.checked += 1;
+        self.expected_seq += 1;
+        self.head = Some(ChainHead { seq: event.seq, hash: event.hash.clone() });
+        self.expected_prev = event.hash;
+        Ok(())
+    }
+
+    /// Checks that the chain reaches `anchor`, a head noted earlier, and
+    /// that the record there is the one noted. Fails with the `seq` where
+    /// the chain stops matching.
+    fn check_anchor(&self, anchor: &ChainHead, hash_at_anchor: Option<&str>) -> Result<(), i64> {
+        if self.expected_seq <= anchor.seq {
+            return Err(self.expected_seq);
+        }
+        if hash_at_anchor != Some(anchor.hash.as_str()) {
+            return Err(anchor.seq);
+        }
+        Ok(())
+    }
+}
+
+/// Where a request came from, for the audit trail.
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestContext {
+    type Rejection = WebauthnError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_string());
+        let user_agent = parts.headers.get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(512).collect());
+        Ok(RequestContext { ip, user_agent })
+    }
+}
+
+/// Appends an event to the audit chain. Failures are logged rather than
+/// returned: the action being audited has already happened by now.
+pub async fn record(app_state: &AppState, ctx: &RequestContext, actor_id: Option<ObjectId>, action: AuditAction, target: Option<String>) {
+    if let Err(e) = append(app_state, ctx, actor_id, action, target).await {
+        error!("Failed to write audit event {:?}: {:?}", action, e);
+    }
+}
+
+async fn append(app_state: &AppState, ctx: &RequestContext, actor_id: Option<ObjectId>, action: AuditAction, target: Option<String>) -> Result<(), mongodb::error::Error> {
+    let collection = app_state.db.collection::<AuditEvent>("audit_events");
+    // Appends must be serialised so each record links to the one before it.
+    // The unique index on `seq` catches writers in other processes.
+    let mut head = app_state.audit_head.lock().await;
+
+    let last = collection.find_one(doc! {}, FindOneOptions::builder().sort(doc! { "seq": -1 }).build()).await?;
+    let (seq, prev_hash) = match last {
+        Some(event) => {
+            if app_state.config.audit_hmac_key.is_some() && !event.keyed {
+                warn!("Audit chain is keyed from seq {} on; set AUDIT_KEYED_SINCE={} so that verification insists on it", event.seq + 1, event.seq + 1);
+            }
+            (event.seq + 1, event.hash)
+        }
+        None => (1, GENESIS_HASH.to_string()),
+    };
+
+    let mut event = AuditEvent {
+        id: None,
+        seq,
+        at: DateTime::now(),
+        actor_id,
+        action,
+        target,
+        ip: ctx.ip.clone(),
+        user_agent: ctx.user_agent.clone(),
+        prev_hash,
+        hash: String::new(),
+        keyed: app_state.config.audit_hmac_key.is_some(),
+    };
+    event.hash = event.compute_hash(app_state.config.audit_hmac_key.as_deref()).unwrap_or_default();
+    let new_head = ChainHead { seq: event.seq, hash: event.hash.clone() };
+    collection.insert_one(event, None).await?;
+    // Shipped off with the rest of the logs, this is the external record a
+    // truncated chain can be checked against.
+    info!(target: "audit_head", "Audit chain head is now {} {}", new_head.seq, new_head.hash);
+    *head = Some(new_head);
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQueryParams {
+    actor: Option<String>,
+    action: Option<AuditAction>,
+    target: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventResponse {
+    pub seq: i64,
+    pub at: String,
+    pub actor_id: Option<String>,
+    pub action: AuditAction,
+    pub target: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub hash: String,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        AuditEventResponse {
+            seq: event.seq,
+            at: event.at.to_string(),
+            actor_id: event.actor_id.map(|id| id.to_string()),
+            action: event.action,
+            target: event.target,
+            ip: event.ip,
+            user_agent: event.user_agent,
+            hash: event.hash,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyQueryParams {
+    /// A head noted earlier, from the logs; see `AuditEvent`.
+    head_seq: Option<i64>,
+    head_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    pub checked: i64,
+    pub first_invalid_seq: Option<i64>,
+    /// The newest record that checked out, to note for next time.
+    pub head: Option<ChainHead>,
+}
+
+async fn find_events(app_state: &AppState, filter: mongodb::bson::Document, limit: i64) -> Result<Vec<AuditEventResponse>, WebauthnError> {
+    let options = FindOptions::builder().sort(doc! { "seq": -1 }).limit(limit).build();
+    let mut cursor = app_state.db.collection::<AuditEvent>("audit_events").find(filter, options).await
+        .map_err(|e| { error!("Failed to fetch audit events: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut events = Vec::new();
+    while let Some(event) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect audit events: {:?}", e); WebauthnError::DatabaseError })? {
+        events.push(event.into());
+    }
+    Ok(events)
+}
+
+pub async fn query_audit_log(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<AuditQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    require_admin(&app_state, &session).await?;
+
+    let mut filter = doc! {};
+    if let Some(actor) = params.actor {
+        filter.insert("actor_id", ObjectId::parse_str(actor)
+            .map_err(|_| WebauthnError::InvalidInput("Invalid actor ID".into()))?);
+    }
+    if let Some(action) = params.action {
+        filter.insert("action", to_bson(&action).map_err(|_| WebauthnError::Unknown)?);
+    }
+    if let Some(target) = params.target {
+        filter.insert("target", target);
+    }
+    let limit = params.limit.unwrap_or(DEFAULT_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+
+    Ok(Json(find_events(&app_state, filter, limit).await?))
+}
+
+/// Walks the whole chain in order and reports the first record whose hash
+/// or back-link doesn't check out. The chain must also reach the given
+/// head, if any, and the last record this process appended, so that
+/// cutting off the newest records is noticed too.
+pub async fn verify_audit_log(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<VerifyQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    require_admin(&app_state, &session).await?;
+    let mut anchors = Vec::new();
+    match (params.head_seq, params.head_hash) {
+        (Some(seq), Some(hash)) => anchors.push(ChainHead { seq, hash }),
+        (None, None) => {}
+        _ => return Err(WebauthnError::InvalidInput("Give both head_seq and head_hash, or neither".into())),
+    }
+    anchors.extend(app_state.audit_head.lock().await.clone());
+
+    let options = FindOptions::builder().sort(doc! { "seq": 1 }).build();
+    let mut cursor = app_state.db.collection::<AuditEvent>("audit_events").find(doc! {}, options).await
+        .map_err(|e| { error!("Failed to fetch audit events: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut verifier = ChainVerifier::new(app_state.config.audit_hmac_key.as_deref(), app_state.config.audit_keyed_since);
+    let mut hashes_at_anchors = vec![None; anchors.len()];
+    while let Some(event) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect audit events: {:?}", e); WebauthnError::DatabaseError })? {
+        for (anchor, hash) in anchors.iter().zip(hashes_at_anchors.iter_mut()) {
+            if anchor.seq == event.seq {
+                *hash = Some(event.hash.clone());
+            }
+        }
+        if let Err(seq) = verifier.check(event) {
+            return Ok(Json(chain_broken(&verifier, seq)));
+        }
+    }
+    for (anchor, hash) in anchors.iter().zip(&hashes_at_anchors) {
+        if let Err(seq) = verifier.check_anchor(anchor, hash.as_deref()) {
+            return Ok(Json(chain_broken(&verifier, seq)));
+        }
+    }
+    Ok(Json(ChainVerification { valid: true, checked: verifier.checked, first_invalid_seq: None, head: verifier.head }))
+}
+
+fn chain_broken(verifier: &ChainVerifier, seq: i64) -> ChainVerification {
+    warn!("Audit chain broken at seq {}", seq);
+    ChainVerification { valid: false, checked: verifier.checked, first_invalid_seq: Some(seq), head: verifier.head.clone() }
+}
+
+/// The caller's own recent security events (logins, logouts, account changes).
+pub async fn my_security_activity(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let actions = to_bson(AuditAction::SECURITY).map_err(|_| WebauthnError::Unknown)?;
+    let filter = doc! {
+        "$or": [{ "actor_id": &user_id }, { "target": user_id.to_hex() }],
+        "action": { "$in": actions },
+    };
+    Ok(Json(find_events(&app_state, filter, ACTIVITY_LIMIT).await?))
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/admin/audit", get(query_audit_log))
+        .route("/api/admin/audit/verify", get(verify_audit_log))
+        .route("/api/auth/activity", get(my_security_activity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "an audit key that is long enough to use";
+
+    fn chain(length: i64, key: Option<&str>) -> Vec<AuditEvent> {
+        legacy_chain(length, key, 1)
+    }
+
+    /// Keyed with `key` from `keyed_since` on, plain SHA-256 before.
+    fn legacy_chain(length: i64, key: Option<&str>, keyed_since: i64) -> Vec<AuditEvent> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        (1..=length).map(|seq| {
+            let mut event = AuditEvent {
+                id: None,
+                seq,
+                at: DateTime::from_millis(1_700_000_000_000 + seq),
+                actor_id: Some(ObjectId::new()),
+                action: AuditAction::LoginSucceeded,
+                target: None,
+                ip: Some("192.0.2.1".into()),
+                user_agent: None,
+                prev_hash: prev_hash.clone(),
+                hash: String::new(),
+                keyed: key.is_some() && seq >= keyed_since,
+            };
+            event.hash = event.compute_hash(key).unwrap();
+            prev_hash = event.hash.clone();
+            event
+        }).collect()
+    }
+
+    fn verify(events: &[AuditEvent], key: Option<&str>, anchor: Option<&ChainHead>) -> Result<i64, i64> {
+        verify_since(events, key, 1, anchor)
+    }
+
+    fn verify_since(events: &[AuditEvent], key: Option<&str>, keyed_since: i64, anchor: Option<&ChainHead>) -> Result<i64, i64> {
+        let mut verifier = ChainVerifier::new(key, keyed_since);
+        let mut hash_at_anchor = None;
+        for event in events {
+            if anchor.is_some_and(|anchor| anchor.seq == event.seq) {
+                hash_at_anchor = Some(event.hash.clone());
+            }
+            verifier.check(event.clone())?;
+        }
+        if let Some(anchor) = anchor {
+            verifier.check_anchor(anchor, hash_at_anchor.as_deref())?;
+        }
+        Ok(verifier.checked)
+    }
+
+    fn head(events: &[AuditEvent]) -> ChainHead {
+        let last = events.last().unwrap();
+        ChainHead { seq: last.seq, hash: last.hash.clone() }
+    }
+
+    #[test]
+    fn intact_chain_verifies() {
+        let events = chain(5, Some(KEY));
+        assert_eq!(verify(&events, Some(KEY), Some(&head(&events))), Ok(5));
+    }
+
+    #[test]
+    fn edited_record_is_caught() {
+        let mut events = chain(5, Some(KEY));
+        events[2].target = Some("someone else".into());
+        assert_eq!(verify(&events, Some(KEY), None), Err(3));
+    }
+
+    #[test]
+    fn edited_record_with_recomputed_hashes_is_caught_without_the_key() {
+        let mut events = chain(5, Some(KEY));
+        events[2].target = Some("someone else".into());
+        // Rehashing from the edit onwards, as a forger without the key would.
+        for i in 2..events.len() {
+            events[i].prev_hash = events[i - 1].hash.clone();
+            events[i].hash = events[i].compute_hash(Some("a guessed key that is long enough too")).unwrap();
+        }
+        assert_eq!(verify(&events, Some(KEY), None), Err(3));
+    }
+
+    #[test]
+    fn unkeyed_records_after_keyed_ones_are_caught() {
+        let mut events = chain(3, Some(KEY));
+        let mut forged = chain(4, None).pop().unwrap();
+        forged.prev_hash = events[2].hash.clone();
+        forged.hash = forged.compute_hash(None).unwrap();
+        events.push(forged);
+        assert_eq!(verify(&events, Some(KEY), None), Err(4));
+    }
+
+    #[test]
+    fn truncated_tail_is_caught_against_a_noted_head() {
+        let mut events = chain(5, Some(KEY));
+        let noted = head(&events);
+        events.truncate(3);
+        assert_eq!(verify(&events, Some(KEY), None), Ok(3));
+        assert_eq!(verify(&events, Some(KEY), Some(&noted)), Err(4));
+    }
+
+    #[test]
+    fn rebuilt_chain_is_caught_against_a_noted_head() {
+        let noted = head(&chain(5, Some(KEY)));
+        // Consistent in itself, as only someone holding the key could make it.
+        let rebuilt = chain(5, Some(KEY));
+        assert_eq!(verify(&rebuilt, Some(KEY), None), Ok(5));
+        assert_eq!(verify(&rebuilt, Some(KEY), Some(&noted)), Err(5));
+    }
+
+    #[test]
+    fn unkeyed_chains_still_verify() {
+        let events = chain(3, None);
+        assert_eq!(verify(&events, None, None), Ok(3));
+        assert_eq!(verify(&chain(3, Some(KEY)), None, None), Err(1));
+    }
+
+    #[test]
+    fn unkeyed_rewrite_from_genesis_is_caught() {
+        let forged = chain(5, None);
+        assert_eq!(verify(&forged, Some(KEY), None), Err(1));
+    }
+
+    #[test]
+    fn legacy_prefix_verifies_up_to_where_keying_began() {
+        let events = legacy_chain(5, Some(KEY), 3);
+        assert_eq!(verify_since(&events, Some(KEY), 3, None), Ok(5));
+        assert_eq!(verify(&events, Some(KEY), None), Err(1));
+
+        let forged = chain(5, None);
+        assert_eq!(verify_since(&forged, Some(KEY), 3, None), Err(3));
+    }
+}
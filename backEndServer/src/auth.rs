@@ -1,7 +1,18 @@
+use crate::api_tokens;
+use crate::attestation::RegistrationCeremony;
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::ceremonies::{self, CeremonyQuery};
+use crate::clone_detection::{self, CounterRegressionPolicy};
 use crate::error::WebauthnError;
+use crate::extract::{Json, Path, Query};
+use crate::profiles;
+use crate::reauth;
+use crate::recovery::{self, RecoveryCodesResponse};
+use crate::sessions;
 use crate::startup::AppState;
+use crate::usernames;
 use axum::{
-    extract::{Extension, Json, Path},
+    extract::Extension,
     http::StatusCode,
     response::IntoResponse,
     Router, routing::post,
@@ -18,16 +29,71 @@ pub struct User {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<mongodb::bson::oid::ObjectId>,
     pub username: String,
+    /// Shown instead of the username when set; see `profiles`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// `usernames::skeleton` of the username, for spotting look-alikes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_skeleton: Option<String>,
     pub keys: Passkey,
     #[serde(with = "uuid_binary_format")]
     pub uuid: Uuid,
+    #[serde(default)]
+    pub role: UserRole,
+    #[serde(default)]
+    pub suspended: bool,
+    /// SHA-256 of each unused recovery code; see `recovery`.
+    #[serde(default)]
+    pub recovery_code_hashes: Vec<String>,
+    /// Set when the passkey's signature counter went backwards; cleared
+    /// when the passkey is replaced.
+    #[serde(default)]
+    pub passkey_possibly_cloned_at: Option<mongodb::bson::DateTime>,
 }
 
-pub async fn is_authenticated(session: &Session) -> Result<mongodb::bson::oid::ObjectId, WebauthnError> {
-    let user_id = session.get::<mongodb::bson::oid::ObjectId>("user_id").await
-        .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?
-        .ok_or_else(|| { info!("User not authenticated"); WebauthnError::Unauthenticated })?;
-    Ok(user_id)
+impl User {
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.username)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    User,
+    Admin,
+}
+
+/// Returns the logged-in user's ID. Sessions belonging to a suspended or
+/// deleted account are rejected.
+pub async fn is_authenticated(app_state: &AppState, session: &Session) -> Result<mongodb::bson::oid::ObjectId, WebauthnError> {
+    Ok(current_user(app_state, session).await?.0)
+}
+
+/// Like `is_authenticated`, but also returns the user document.
+pub async fn current_user(app_state: &AppState, session: &Session) -> Result<(mongodb::bson::oid::ObjectId, User), WebauthnError> {
+    // A bearer token, when present, has already been checked by `bearer_auth`
+    // and takes the place of the session.
+    let user_id = match api_tokens::current_user_id() {
+        Some(user_id) => user_id,
+        None => {
+            let user_id = session.get::<mongodb::bson::oid::ObjectId>("user_id").await
+                .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?
+                .ok_or_else(|| { info!("User not authenticated"); WebauthnError::Unauthenticated })?;
+            sessions::validate(app_state, session, &user_id).await?;
+            user_id
+        }
+    };
+
+    let user = app_state.db.collection::<User>("users").find_one(doc! { "_id": &user_id }, None).await
+        .map_err(|e| { error!("Database error during user lookup: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or_else(|| { info!("Session user {} no longer exists", user_id); WebauthnError::Unauthenticated })?;
+    if user.suspended {
+        info!("Rejecting session of suspended user {}", user_id);
+        return Err(WebauthnError::AccountSuspended);
+    }
+    Ok((user_id, user))
 }
 
 mod uuid_binary_format {
@@ -51,26 +117,36 @@ mod uuid_binary_format {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RegisterParams {
+    display_name: Option<String>,
+}
+
 pub async fn start_register(
     Extension(app_state): Extension<AppState>,
     session: Session,
     Path(username): Path<String>,
+    Query(params): Query<RegisterParams>,
 ) -> Result<impl IntoResponse, WebauthnError> {
+    let username = usernames::validate(&username)?;
     info!("Starting registration for user: {}", username);
-    let user_collection = app_state.db.collection::<User>("users");
-    let user_option = user_collection.find_one(doc! { "username": username.clone() }, None).await
-        .map_err(|e| { error!("Database error during user search: {:?}", e); WebauthnError::DatabaseError })?;
+    let display_name = match params.display_name {
+        Some(display_name) => profiles::normalize_display_name(&display_name)?,
+        None => None,
+    };
+    let user_option = profiles::find_by_username(&app_state, &username).await?;
 
     if user_option.is_none() {
+        usernames::ensure_not_confusable(&app_state, &username, None).await?;
         let user_unique_id = Uuid::new_v4();
         let _ = session.remove_value("reg_state").await;
 
-        match app_state.webauthn.start_passkey_registration(user_unique_id, &username, &username, None) {
+        let webauthn_display_name = display_name.as_deref().unwrap_or(&username);
+        match app_state.authenticator_policy.start_registration(&app_state.webauthn, user_unique_id, &username, webauthn_display_name) {
             Ok((ccr, reg_state)) => {
-                session.insert("reg_state", (username.clone(), user_unique_id, reg_state)).await
-                    .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+                let response = ceremonies::begin(&app_state, &session, "reg_state", (username.clone(), display_name, user_unique_id, reg_state), ccr).await?;
                 info!("Registration challenge created for user: {}", username);
-                Ok(Json(ccr))
+                Ok(response)
             }
             Err(e) => {
                 error!("WebAuthn registration initialization error: {:?}", e);
@@ -86,24 +162,35 @@ pub async fn start_register(
 pub async fn finish_register(
     Extension(app_state): Extension<AppState>,
     session: Session,
+    ctx: RequestContext,
+    Query(query): Query<CeremonyQuery>,
     Json(reg): Json<RegisterPublicKeyCredential>,
 ) -> Result<impl IntoResponse, WebauthnError> {
-    let (username, user_unique_id, reg_state) = match session.get::<(String, Uuid, PasskeyRegistration)>("reg_state").await {
-        Ok(Some(data)) => data,
-        Ok(None) => { error!("No registration state found in session"); return Err(WebauthnError::CorruptSession); }
-        Err(e) => { error!("Failed to get session data: {:?}", e); return Err(WebauthnError::InvalidSessionState(e)); }
-    };
-
-    let _ = session.remove_value("reg_state").await;
+    let (username, display_name, user_unique_id, reg_state): (String, Option<String>, Uuid, RegistrationCeremony) =
+        ceremonies::take(&session, "reg_state", query.ceremony).await?;
 
-    match app_state.webauthn.finish_passkey_registration(&reg, &reg_state) {
+    match reg_state.finish(&app_state.webauthn, &reg) {
         Ok(passkey) => {
-            let user = User { id: None, username: username.clone(), keys: passkey, uuid: user_unique_id }; // Clone username
+            // Admins are appointed, never registered; see `admin::bootstrap_admin`.
+            let role = UserRole::User;
+            let (recovery_codes, recovery_code_hashes) = recovery::generate_codes(&user_unique_id);
+            let user = User { id: None, username: username.clone(), display_name, username_skeleton: Some(usernames::skeleton(&username)), keys: passkey, uuid: user_unique_id, role, suspended: false, recovery_code_hashes, passkey_possibly_cloned_at: None }; // Clone username
             let user_collection = app_state.db.collection::<User>("users");
-            user_collection.insert_one(user, None).await
-                .map_err(|e| { error!("Failed to store user: {:?}", e); WebauthnError::DatabaseError })?;
+            // Someone may have taken the name since registration started.
+            let result = user_collection.insert_one(user, None).await
+                .map_err(|e| {
+                    if profiles::is_duplicate_key(&e) {
+                        info!("User '{}' already exists", username);
+                        return WebauthnError::UserExists;
+                    }
+                    error!("Failed to store user: {:?}", e);
+                    WebauthnError::DatabaseError
+                })?;
+            let user_id = result.inserted_id.as_object_id();
+            audit::record(&app_state, &ctx, user_id, AuditAction::UserRegistered, user_id.map(|id| id.to_hex())).await;
             info!("User registration completed successfully for: {}", username);
-            Ok(StatusCode::OK)
+            // The codes are shown once, now; only their hashes are kept.
+            Ok(Json(RecoveryCodesResponse { recovery_codes }))
         }
         Err(e) => {
             error!("WebAuthn registration completion error: {:?}", e);
@@ -120,17 +207,19 @@ pub async fn start_authentication(
     info!("Starting authentication for user: {}", username);
     let _ = session.remove_value("auth_state").await;
 
-    let user_collection = app_state.db.collection::<User>("users");
-    let user = user_collection.find_one(doc! { "username": username.clone() }, None).await
-        .map_err(|e| { error!("Database error during user search: {:?}", e); WebauthnError::DatabaseError })?
+    let user = profiles::find_by_username(&app_state, &username).await?
         .ok_or_else(|| { info!("User '{}' not found", username); WebauthnError::UserNotFound })?;
+    if user.suspended {
+        info!("Suspended user '{}' tried to log in", username);
+        return Err(WebauthnError::AccountSuspended);
+    }
+    clone_detection::check_marked(app_state.config.counter_regression_policy, &user)?;
 
-    match app_state.webauthn.start_passkey_authentication(&[user.keys.clone()]) {
+    match app_state.webauthn.start_passkey_authentication(std::slice::from_ref(&user.keys)) {
         Ok((rcr, auth_state)) => {
-            session.insert("auth_state", (user.uuid, auth_state)).await
-                .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+            let response = ceremonies::begin(&app_state, &session, "auth_state", (user.uuid, auth_state), rcr).await?;
             info!("Authentication challenge created for user: {}", username);
-            Ok(Json(rcr))
+            Ok(response)
         }
         Err(e) => {
             error!("WebAuthn authentication initialization error: {:?}", e);
@@ -142,32 +231,60 @@ pub async fn start_authentication(
 pub async fn finish_authentication(
     Extension(app_state): Extension<AppState>,
     session: Session,
+    ctx: RequestContext,
+    Query(query): Query<CeremonyQuery>,
     Json(auth): Json<PublicKeyCredential>,
 ) -> Result<impl IntoResponse, WebauthnError> {
-    let (user_uuid, auth_state) = match session.get::<(Uuid, PasskeyAuthentication)>("auth_state").await {
-        Ok(Some(data)) => data,
-        Ok(None) => { error!("No authentication state found in session"); return Err(WebauthnError::CorruptSession); }
-        Err(e) => { error!("Failed to get session data: {:?}", e); return Err(WebauthnError::InvalidSessionState(e)); }
-    };
-
-    let _ = session.remove_value("auth_state").await;
+    let (user_uuid, auth_state): (Uuid, PasskeyAuthentication) = ceremonies::take(&session, "auth_state", query.ceremony).await?;
 
     let user_collection = app_state.db.collection::<User>("users");
     let binary = Binary { subtype: mongodb::bson::spec::BinarySubtype::Generic, bytes: user_uuid.as_bytes().to_vec() };
     let user = user_collection.find_one(doc! { "uuid": binary }, None).await
         .map_err(|e| { error!("Database error during user lookup: {:?}", e); WebauthnError::DatabaseError })?
         .ok_or_else(|| { error!("User with UUID {:?} not found", user_uuid); WebauthnError::UserNotFound })?;
+    if user.suspended {
+        info!("Suspended user with UUID {:?} tried to log in", user_uuid);
+        return Err(WebauthnError::AccountSuspended);
+    }
 
-    match app_state.webauthn.finish_passkey_authentication(&auth, &auth_state) {
-        Ok(auth_result) => {
-            let mut updated_user = user.clone();
-            updated_user.keys.update_credential(&auth_result);
+    let policy = app_state.config.counter_regression_policy;
+    // Checked again: the mark may have been set since the start.
+    let trusted = clone_detection::check_marked(policy, &user)?;
+    let result = match app_state.webauthn.finish_passkey_authentication(&auth, &auth_state) {
+        Ok(auth_result) => Ok(Some(auth_result)),
+        // Everything but the counter checked out; the policy decides.
+        Err(webauthn_rs::prelude::WebauthnError::CredentialPossibleCompromise) => {
+            if let Some(id) = user.id {
+                clone_detection::flag_possible_clone(&app_state, &ctx, &id).await;
+            }
+            if policy == CounterRegressionPolicy::Block {
+                audit::record(&app_state, &ctx, None, AuditAction::LoginFailed, user.id.map(|id| id.to_hex())).await;
+                return Err(WebauthnError::PasskeyPossiblyCloned);
+            }
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    };
 
+    match result {
+        Ok(auth_result) => {
             if let Some(id) = user.id {
-                user_collection.replace_one(doc! { "_id": id }, updated_user, None).await
-                    .map_err(|e| { error!("Failed to update user credential: {:?}", e); WebauthnError::DatabaseError })?;
-                session.insert("user_id", id).await
-                    .map_err(|e| { error!("Failed to store user ID in session: {:?}", e); WebauthnError::CorruptSession })?;
+                // After a counter regression there is no result to update from.
+                // Only the passkey is written back: the rest of `user` may
+                // have changed since it was read, e.g. by a clone flag.
+                if let Some(auth_result) = &auth_result {
+                    let mut keys = user.keys.clone();
+                    if keys.update_credential(auth_result).is_some() {
+                        let keys = mongodb::bson::to_bson(&keys).map_err(|_| WebauthnError::Unknown)?;
+                        user_collection.update_one(doc! { "_id": id }, doc! { "$set": { "keys": keys } }, None).await
+                            .map_err(|e| { error!("Failed to update user credential: {:?}", e); WebauthnError::DatabaseError })?;
+                    }
+                }
+                sessions::establish(&app_state, &session, &ctx, id).await?;
+                if trusted && (auth_result.is_some() || policy == CounterRegressionPolicy::Warn) {
+                    reauth::mark_verified(&session).await?;
+                }
+                audit::record(&app_state, &ctx, Some(id), AuditAction::LoginSucceeded, Some(id.to_hex())).await;
                 info!("Authentication successful for user with UUID: {:?}", user_uuid);
                 Ok(StatusCode::OK)
             } else {
@@ -177,14 +294,23 @@ pub async fn finish_authentication(
         }
         Err(e) => {
             error!("WebAuthn authentication completion error: {:?}", e);
+            audit::record(&app_state, &ctx, None, AuditAction::LoginFailed, user.id.map(|id| id.to_hex())).await;
             Err(WebauthnError::InvalidCredential)
         }
     }
 }
 
-pub async fn logout(session: Session) -> Result<impl IntoResponse, WebauthnError> {
-    session.remove_value("user_id").await
+pub async fn logout(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = session.get::<mongodb::bson::oid::ObjectId>("user_id").await
         .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+    sessions::end_current(&app_state, &session).await?;
+    if let Some(user_id) = user_id {
+        audit::record(&app_state, &ctx, Some(user_id), AuditAction::Logout, Some(user_id.to_hex())).await;
+    }
     Ok(StatusCode::OK)
 }
 
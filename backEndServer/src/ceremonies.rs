@@ -0,0 +1,112 @@
+use mongodb::bson::DateTime;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tower_sessions::Session;
+use uuid::Uuid;
+
+use crate::error::WebauthnError;
+use crate::extract::Json;
+use crate::startup::AppState;
+
+/// Session key listing the IDs of finished ceremonies, so that a replayed
+/// finish can be told apart from one that never started.
+const USED_CEREMONIES_KEY: &str = "used_ceremonies";
+/// Finished ceremonies remembered per session; older ones are forgotten.
+const USED_CEREMONIES_KEPT: usize = 16;
+
+/// The server's half of a WebAuthn ceremony, kept in the session between
+/// `*_start` and `*_finish`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Ceremony<T> {
+    id: Uuid,
+    /// Milliseconds since the epoch.
+    expires_at: i64,
+    state: T,
+}
+
+/// A `*_start` response: the options for the browser, plus the ceremony's
+/// ID for the client to pass back to `*_finish` as `?ceremony=`.
+#[derive(Debug, Serialize)]
+pub struct CeremonyResponse<O> {
+    pub ceremony_id: Uuid,
+    pub expires_at: String,
+    #[serde(flatten)]
+    pub options: O,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CeremonyQuery {
+    /// Optional for clients that predate ceremony IDs; without it a
+    /// superseded or replayed ceremony cannot be detected.
+    pub ceremony: Option<Uuid>,
+}
+
+/// Stores `state` under `key` as a new ceremony, replacing any unfinished
+/// one, and wraps `options` for the response.
+pub async fn begin<S: Serialize, O>(
+    app_state: &AppState,
+    session: &Session,
+    key: &str,
+    state: S,
+    options: O,
+) -> Result<Json<CeremonyResponse<O>>, WebauthnError> {
+    let id = Uuid::new_v4();
+    let expires_at = DateTime::now().timestamp_millis() + app_state.config.ceremony_ttl_secs as i64 * 1000;
+    session.insert(key, Ceremony { id, expires_at, state }).await
+        .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+    Ok(Json(CeremonyResponse {
+        ceremony_id: id,
+        expires_at: DateTime::from_millis(expires_at).to_string(),
+        options,
+    }))
+}
+
+async fn used_ceremonies(session: &Session) -> Result<Vec<(Uuid, i64)>, WebauthnError> {
+    Ok(session.get::<Vec<(Uuid, i64)>>(USED_CEREMONIES_KEY).await?.unwrap_or_default())
+}
+
+async fn was_used(session: &Session, id: Option<Uuid>) -> Result<bool, WebauthnError> {
+    let Some(id) = id else { return Ok(false) };
+    Ok(used_ceremonies(session).await?.iter().any(|(used, _)| *used == id))
+}
+
+/// Takes the ceremony stored under `key` for a `*_finish`. Fails with
+/// `ChallengeAlreadyUsed` when `id` was finished before, `ChallengeMissing`
+/// when there is nothing to finish or `id` has been superseded by a newer
+/// start, and `ChallengeExpired` past the TTL. Whatever the outcome, a
+/// ceremony is only ever taken once.
+pub async fn take<T: DeserializeOwned>(session: &Session, key: &str, id: Option<Uuid>) -> Result<T, WebauthnError> {
+    let Some(ceremony) = session.get::<Ceremony<T>>(key).await? else {
+        if was_used(session, id).await? {
+            info!("Ceremony {:?} was already finished", id);
+            return Err(WebauthnError::ChallengeAlreadyUsed);
+        }
+        info!("No {} in session", key);
+        return Err(WebauthnError::ChallengeMissing);
+    };
+    if id.is_some_and(|id| id != ceremony.id) {
+        // Leave the newer ceremony alone; its own finish may still come.
+        if was_used(session, id).await? {
+            info!("Ceremony {:?} was already finished", id);
+            return Err(WebauthnError::ChallengeAlreadyUsed);
+        }
+        info!("Ceremony {:?} was superseded by {}", id, ceremony.id);
+        return Err(WebauthnError::ChallengeMissing);
+    }
+
+    let _ = session.remove_value(key).await;
+    let now = DateTime::now().timestamp_millis();
+    if now > ceremony.expires_at {
+        info!("Ceremony {} expired", ceremony.id);
+        return Err(WebauthnError::ChallengeExpired);
+    }
+
+    let mut used = used_ceremonies(session).await?;
+    used.retain(|(_, expires_at)| *expires_at > now);
+    used.push((ceremony.id, ceremony.expires_at));
+    if used.len() > USED_CEREMONIES_KEPT {
+        used.drain(..used.len() - USED_CEREMONIES_KEPT);
+    }
+    session.insert(USED_CEREMONIES_KEY, used).await
+        .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+    Ok(ceremony.state)
+}
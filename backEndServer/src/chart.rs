@@ -0,0 +1,226 @@
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+
+use axum::{
+    extract::Extension,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Router, routing::get,
+};
+use mongodb::bson::oid::ObjectId;
+use resvg::{tiny_skia, usvg};
+use serde::Deserialize;
+use tower_sessions::Session;
+
+use crate::error::WebauthnError;
+use crate::extract::{Path, Query};
+use crate::permissions::find_viewable_poll;
+use crate::polls::{option_statistics, OptionStatistics, Poll};
+use crate::startup::AppState;
+
+const WIDTH: f64 = 800.0;
+const CHART_MAX_AGE_SECS: u64 = 60;
+const PALETTE: &[&str] = &["#8b5cf6", "#ec4899", "#3b82f6", "#10b981", "#f59e0b", "#ef4444", "#6366f1", "#14b8a6"];
+const FONT: &str = "font-family=\"DejaVu Sans, Arial, Helvetica, sans-serif\"";
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    #[default]
+    Bar,
+    Pie,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChartQueryParams {
+    #[serde(default, rename = "type")]
+    kind: ChartKind,
+}
+
+/// Escapes text for SVG and HTML, in content and in quoted attributes.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+fn vote_label(option: &OptionStatistics) -> String {
+    format!("{} vote{} ({:.1}%)", option.votes, if option.votes == 1 { "" } else { "s" }, option.percentage)
+}
+
+/// Draws the results as a standalone SVG document.
+pub fn render_svg(title: &str, options: &[OptionStatistics], total_votes: i32, kind: ChartKind) -> String {
+    match kind {
+        ChartKind::Bar => bar_chart(title, options, total_votes),
+        ChartKind::Pie => pie_chart(title, options, total_votes),
+    }
+}
+
+fn svg_open(svg: &mut String, height: f64, title: &str, total_votes: i32) {
+    let _ = write!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{height}\" viewBox=\"0 0 {WIDTH} {height}\">");
+    let _ = write!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>");
+    let _ = write!(svg, "<text x=\"32\" y=\"52\" {FONT} font-size=\"26\" font-weight=\"bold\" fill=\"#1f2937\">{}</text>", escape(title));
+    let _ = write!(svg, "<text x=\"32\" y=\"80\" {FONT} font-size=\"15\" fill=\"#6b7280\">{total_votes} total vote{}</text>", if total_votes == 1 { "" } else { "s" });
+}
+
+fn bar_chart(title: &str, options: &[OptionStatistics], total_votes: i32) -> String {
+    let row_height = 56.0;
+    let top = 112.0;
+    let bar_width = WIDTH - 64.0;
+    let height = top + row_height * options.len() as f64 + 24.0;
+
+    let mut svg = String::new();
+    svg_open(&mut svg, height, title, total_votes);
+    for (index, option) in options.iter().enumerate() {
+        let y = top + row_height * index as f64;
+        let color = PALETTE[index % PALETTE.len()];
+        let filled = bar_width * option.percentage / 100.0;
+        let _ = write!(svg, "<text x=\"32\" y=\"{y}\" {FONT} font-size=\"16\" fill=\"#1f2937\">{}</text>", escape(&option.text));
+        let _ = write!(svg, "<text x=\"{}\" y=\"{y}\" {FONT} font-size=\"14\" fill=\"#6b7280\" text-anchor=\"end\">{}</text>", WIDTH - 32.0, vote_label(option));
+        let _ = write!(svg, "<rect x=\"32\" y=\"{}\" width=\"{bar_width}\" height=\"18\" rx=\"9\" fill=\"#f3f4f6\"/>", y + 10.0);
+        if filled > 0.0 {
+            let _ = write!(svg, "<rect x=\"32\" y=\"{}\" width=\"{filled:.2}\" height=\"18\" rx=\"9\" fill=\"{color}\"/>", y + 10.0);
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn pie_chart(title: &str, options: &[OptionStatistics], total_votes: i32) -> String {
+    let radius: f64 = 140.0;
+    let (cx, cy) = (200.0, 120.0 + radius);
+    let legend_row = 32.0;
+    let height = (cy + radius + 32.0).max(120.0 + legend_row * options.len() as f64 + 24.0);
+
+    let mut svg = String::new();
+    svg_open(&mut svg, height, title, total_votes);
+    if total_votes == 0 {
+        let _ = write!(svg, "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{radius}\" fill=\"#f3f4f6\"/>");
+        let _ = write!(svg, "<text x=\"{cx}\" y=\"{cy}\" {FONT} font-size=\"16\" fill=\"#6b7280\" text-anchor=\"middle\">No votes yet</text>");
+    } else {
+        // Angles start at 12 o'clock and run clockwise.
+        let mut start = -PI / 2.0;
+        for (index, option) in options.iter().enumerate() {
+            let color = PALETTE[index % PALETTE.len()];
+            let sweep = 2.0 * PI * option.votes as f64 / total_votes as f64;
+            if option.votes == total_votes {
+                let _ = write!(svg, "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{radius}\" fill=\"{color}\"/>");
+            } else if option.votes > 0 {
+                let end = start + sweep;
+                let (x1, y1) = (cx + radius * start.cos(), cy + radius * start.sin());
+                let (x2, y2) = (cx + radius * end.cos(), cy + radius * end.sin());
+                let large_arc = if sweep > PI { 1 } else { 0 };
+                let _ = write!(svg, "<path d=\"M{cx},{cy} L{x1:.2},{y1:.2} A{radius},{radius} 0 {large_arc} 1 {x2:.2},{y2:.2} Z\" fill=\"{color}\" stroke=\"#ffffff\" stroke-width=\"2\"/>");
+            }
+            start += sweep;
+        }
+    }
+
+    let legend_x = cx + radius + 60.0;
+    for (index, option) in options.iter().enumerate() {
+        let y = 128.0 + legend_row * index as f64;
+        let color = PALETTE[index % PALETTE.len()];
+        let _ = write!(svg, "<rect x=\"{legend_x}\" y=\"{}\" width=\"14\" height=\"14\" rx=\"3\" fill=\"{color}\"/>", y - 12.0);
+        let _ = write!(svg, "<text x=\"{}\" y=\"{y}\" {FONT} font-size=\"15\" fill=\"#1f2937\">{} · {}</text>", legend_x + 24.0, escape(&option.text), vote_label(option));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Fonts are looked up once; scanning the system font directories is slow.
+fn fontdb() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        let mut fonts = usvg::fontdb::Database::new();
+        fonts.load_system_fonts();
+        if fonts.is_empty() {
+            warn!("No system fonts found; chart PNGs will have no text");
+        }
+        Arc::new(fonts)
+    }).clone()
+}
+
+/// Rasterises an SVG produced by `render_svg`. CPU-bound, so call it from a
+/// blocking task.
+pub fn render_png(svg: &str) -> Result<Vec<u8>, WebauthnError> {
+    let options = usvg::Options { fontdb: fontdb(), ..usvg::Options::default() };
+    let tree = usvg::Tree::from_str(svg, &options)
+        .map_err(|e| { error!("Failed to parse chart SVG: {:?}", e); WebauthnError::Unknown })?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or(WebauthnError::Unknown)?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| { error!("Failed to encode chart PNG: {:?}", e); WebauthnError::Unknown })
+}
+
+pub async fn render_png_blocking(svg: String) -> Result<Vec<u8>, WebauthnError> {
+    tokio::task::spawn_blocking(move || render_png(&svg)).await
+        .map_err(|e| { error!("Chart rendering task failed: {:?}", e); WebauthnError::Unknown })?
+}
+
+/// Changes whenever the drawn results could: votes only ever add to
+/// `total_votes`, and a reset or its undo moves `round`.
+fn chart_etag(poll_id: &ObjectId, poll: &Poll, kind: ChartKind, extension: &str) -> String {
+    format!("\"{}-{}-{}-{:?}-{}\"", poll_id.to_hex(), poll.round, poll.total_votes, kind, extension).to_lowercase()
+}
+
+async fn chart_response(
+    app_state: AppState,
+    session: Session,
+    headers: HeaderMap,
+    poll_id: String,
+    kind: ChartKind,
+    png: bool,
+) -> Result<Response, WebauthnError> {
+    let (poll_id, poll) = find_viewable_poll(&app_state, &session, &poll_id).await?;
+    let etag = chart_etag(&poll_id, &poll, kind, if png { "png" } else { "svg" });
+    // Restricted polls must not end up in shared caches.
+    let cache_control = if poll.org_id.is_none() && !poll.is_hidden {
+        format!("public, max-age={CHART_MAX_AGE_SECS}")
+    } else {
+        format!("private, max-age={CHART_MAX_AGE_SECS}")
+    };
+    let cache_headers = [(ETAG, etag.clone()), (CACHE_CONTROL, cache_control), (VARY, "Cookie".to_string())];
+
+    let not_modified = headers.get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim().trim_start_matches("W/") == etag));
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    let svg = render_svg(&poll.title, &option_statistics(&poll.options, poll.total_votes), poll.total_votes, kind);
+    if png {
+        let png = render_png_blocking(svg).await?;
+        Ok((cache_headers, [(CONTENT_TYPE, "image/png")], png).into_response())
+    } else {
+        Ok((cache_headers, [(CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+    }
+}
+
+pub async fn chart_svg(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    headers: HeaderMap,
+    Path(poll_id): Path<String>,
+    Query(params): Query<ChartQueryParams>,
+) -> Result<Response, WebauthnError> {
+    chart_response(app_state, session, headers, poll_id, params.kind, false).await
+}
+
+pub async fn chart_png(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    headers: HeaderMap,
+    Path(poll_id): Path<String>,
+    Query(params): Query<ChartQueryParams>,
+) -> Result<Response, WebauthnError> {
+    chart_response(app_state, session, headers, poll_id, params.kind, true).await
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/polls/:pollId/chart.svg", get(chart_svg))
+        .route("/api/polls/:pollId/chart.png", get(chart_png))
+}
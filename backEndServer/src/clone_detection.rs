@@ -0,0 +1,71 @@
+use std::str::FromStr;
+
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::User;
+use crate::error::WebauthnError;
+use crate::startup::AppState;
+
+/// What to do when a passkey's signature counter fails to increase, which
+/// suggests a second copy of its private key is in use.
+///
+/// webauthn-rs reports this as `CredentialPossibleCompromise` only after the
+/// assertion has otherwise been verified, so letting the login through is
+/// safe as far as the signature goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterRegressionPolicy {
+    /// Log the user in; the event is only recorded.
+    Warn,
+    /// Log the user in, but without counting the login as a recent
+    /// verification, so destructive actions need a clean re-authentication.
+    StepUp,
+    /// Refuse the login.
+    Block,
+}
+
+impl FromStr for CounterRegressionPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warn" => Ok(CounterRegressionPolicy::Warn),
+            "step_up" => Ok(CounterRegressionPolicy::StepUp),
+            "block" => Ok(CounterRegressionPolicy::Block),
+            _ => Err("expected warn, step_up or block".into()),
+        }
+    }
+}
+
+/// Marks the user's passkey as possibly cloned and records the event. The
+/// mark stays until the passkey is replaced through `recovery`, and while
+/// it does, `check_marked` keeps applying the policy to every login.
+pub async fn flag_possible_clone(app_state: &AppState, ctx: &RequestContext, user_id: &ObjectId) {
+    warn!("Signature counter of user {}'s passkey did not increase; it may be cloned", user_id);
+    if let Err(e) = app_state.db.collection::<User>("users")
+        .update_one(doc! { "_id": user_id }, doc! { "$set": { "passkey_possibly_cloned_at": DateTime::now() } }, None).await
+    {
+        error!("Failed to flag passkey as possibly cloned: {:?}", e);
+    }
+    audit::record(app_state, ctx, Some(*user_id), AuditAction::PasskeyPossiblyCloned, Some(user_id.to_hex())).await;
+}
+
+/// Applies the policy to a user whose passkey has been marked by
+/// `flag_possible_clone`, which a later login with an increasing counter
+/// does not undo. Under `Block` the login is refused outright. Returns
+/// whether the login may count as a recent verification, which under
+/// `StepUp` it can't: re-authenticating with the same passkey proves
+/// nothing, so destructive actions wait for recovery.
+pub fn check_marked(policy: CounterRegressionPolicy, user: &User) -> Result<bool, WebauthnError> {
+    if user.passkey_possibly_cloned_at.is_none() {
+        return Ok(true);
+    }
+    match policy {
+        CounterRegressionPolicy::Warn => Ok(true),
+        CounterRegressionPolicy::StepUp => Ok(false),
+        CounterRegressionPolicy::Block => {
+            info!("User '{}' tried to log in with a passkey marked as possibly cloned", user.username);
+            Err(WebauthnError::PasskeyPossiblyCloned)
+        }
+    }
+}
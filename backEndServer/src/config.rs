@@ -0,0 +1,131 @@
+use std::env;
+
+use crate::account::AccountRetention;
+use crate::attestation::{AttestationMode, ResidentKeyPolicy};
+use crate::clone_detection::CounterRegressionPolicy;
+use crate::rate_limit::RateLimit;
+
+const MIN_SECRET_LEN: usize = 32;
+
+/// Runtime settings, read once from the environment at startup.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Key for the audit chain's HMACs; see `audit::AuditEvent`.
+    pub audit_hmac_key: Option<String>,
+    /// First `seq` written with `audit_hmac_key`; records before it may be
+    /// unkeyed, from it on they must not be.
+    pub audit_keyed_since: i64,
+    /// Lets the first admin be appointed; see `admin::bootstrap_admin`.
+    pub admin_bootstrap_token: Option<String>,
+    /// Open reports after which a poll is hidden pending moderation.
+    pub report_hide_threshold: u64,
+    /// How long after a reset it can still be undone.
+    pub reset_undo_window_secs: u64,
+    /// Longest a login session lasts, however active it is.
+    pub session_max_lifetime_secs: u64,
+    /// How long a passkey prompt can be answered after it is started.
+    pub ceremony_ttl_secs: u64,
+    /// How long a passkey re-authentication unlocks destructive actions for.
+    pub reauth_window_secs: u64,
+    /// Where this server is reachable from outside, for absolute links in
+    /// previews and embeds.
+    pub public_url: String,
+    /// Where the web app is served; shared links point here.
+    pub frontend_url: String,
+    /// Registration policy; see `attestation::AuthenticatorPolicy`.
+    pub attestation: AttestationMode,
+    /// FIDO Metadata Service BLOB holding the trusted attestation roots.
+    pub attestation_metadata_path: Option<String>,
+    /// Authenticators to accept from the metadata; empty means all of them.
+    pub attestation_trusted_aaguids: Vec<String>,
+    pub resident_key: ResidentKeyPolicy,
+    /// What a passkey whose signature counter went backwards can still do.
+    pub counter_regression_policy: CounterRegressionPolicy,
+    /// What happens to a deleted account's polls and votes.
+    pub account_retention: AccountRetention,
+    /// Lets webhooks target loopback and private networks. Only for
+    /// development against a local receiver; see `webhooks::is_public_ip`.
+    pub webhook_allow_private_targets: bool,
+    /// Token buckets for `rate_limit`, each given as `<capacity>/<period_secs>`.
+    pub rate_limit_auth_ip: RateLimit,
+    pub rate_limit_auth_account: RateLimit,
+    pub rate_limit_vote_ip: RateLimit,
+    pub rate_limit_vote_account: RateLimit,
+    pub rate_limit_create_poll_ip: RateLimit,
+    pub rate_limit_create_poll_account: RateLimit,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Config {
+            audit_hmac_key: secret_var("AUDIT_HMAC_KEY"),
+            audit_keyed_since: num_var("AUDIT_KEYED_SINCE", 1),
+            admin_bootstrap_token: secret_var("ADMIN_BOOTSTRAP_TOKEN"),
+            report_hide_threshold: num_var("REPORT_HIDE_THRESHOLD", 3),
+            reset_undo_window_secs: num_var("RESET_UNDO_WINDOW_SECS", 300),
+            session_max_lifetime_secs: num_var("SESSION_MAX_LIFETIME_SECS", 12 * 60 * 60),
+            ceremony_ttl_secs: num_var("CEREMONY_TTL_SECS", 5 * 60),
+            reauth_window_secs: num_var("REAUTH_WINDOW_SECS", 5 * 60),
+            public_url: url_var("PUBLIC_URL", "http://localhost:8080"),
+            frontend_url: url_var("FRONTEND_URL", "http://localhost:8081"),
+            attestation: parsed_var("ATTESTATION", "none"),
+            attestation_metadata_path: env::var("ATTESTATION_METADATA_PATH").ok().filter(|path| !path.is_empty()),
+            attestation_trusted_aaguids: list_var("ATTESTATION_TRUSTED_AAGUIDS"),
+            resident_key: parsed_var("RESIDENT_KEY", "discouraged"),
+            counter_regression_policy: parsed_var("COUNTER_REGRESSION_POLICY", "block"),
+            account_retention: parsed_var("ACCOUNT_DELETION_RETENTION", "anonymize"),
+            webhook_allow_private_targets: bool_var("WEBHOOK_ALLOW_PRIVATE_TARGETS", false),
+            rate_limit_auth_ip: rate_var("RATE_LIMIT_AUTH_IP", "20/60"),
+            rate_limit_auth_account: rate_var("RATE_LIMIT_AUTH_ACCOUNT", "5/60"),
+            rate_limit_vote_ip: rate_var("RATE_LIMIT_VOTE_IP", "60/60"),
+            rate_limit_vote_account: rate_var("RATE_LIMIT_VOTE_ACCOUNT", "30/60"),
+            rate_limit_create_poll_ip: rate_var("RATE_LIMIT_CREATE_POLL_IP", "30/3600"),
+            rate_limit_create_poll_account: rate_var("RATE_LIMIT_CREATE_POLL_ACCOUNT", "10/3600"),
+        }
+    }
+}
+
+fn list_var(name: &str) -> Vec<String> {
+    env::var(name)
+        .map(|value| value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// An optional secret, which must be long enough not to be guessed.
+fn secret_var(name: &str) -> Option<String> {
+    let value = env::var(name).ok().filter(|value| !value.is_empty())?;
+    if value.len() < MIN_SECRET_LEN {
+        panic!("{} must be at least {} characters long", name, MIN_SECRET_LEN);
+    }
+    Some(value)
+}
+
+fn url_var(name: &str, default: &str) -> String {
+    let value = env::var(name).unwrap_or_else(|_| default.to_string());
+    url::Url::parse(&value).unwrap_or_else(|_| panic!("{} must be a URL", name));
+    value.trim_end_matches('/').to_string()
+}
+
+fn rate_var(name: &str, default: &str) -> RateLimit {
+    let value = env::var(name).unwrap_or_else(|_| default.to_string());
+    value.parse().unwrap_or_else(|e| panic!("{} is not a valid rate limit: {}", name, e))
+}
+
+fn parsed_var<T: std::str::FromStr<Err = String>>(name: &str, default: &str) -> T {
+    let value = env::var(name).unwrap_or_else(|_| default.to_string());
+    value.parse().unwrap_or_else(|e| panic!("{} is invalid: {}", name, e))
+}
+
+fn bool_var(name: &str, default: bool) -> bool {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| panic!("{} must be true or false", name)),
+        Err(_) => default,
+    }
+}
+
+fn num_var<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| panic!("{} must be a number", name)),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,242 @@
+use std::fmt::Write;
+
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    Router, routing::get,
+};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use url::Url;
+
+use crate::auth::User;
+use crate::chart::escape;
+use crate::error::WebauthnError;
+use crate::extract::{Json, Path, Query};
+use crate::permissions::find_viewable_poll;
+use crate::polls::{option_statistics, poll_response, PollResponse};
+use crate::startup::AppState;
+
+const PROVIDER_NAME: &str = "WebAuthn Polls";
+const EMBED_WIDTH: u32 = 480;
+/// Height of the widget's header and footer, plus one row per option.
+const EMBED_BASE_HEIGHT: u32 = 140;
+const EMBED_ROW_HEIGHT: u32 = 48;
+const EMBED_REFRESH_SECS: u32 = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct OEmbedQueryParams {
+    url: String,
+    format: Option<String>,
+    maxwidth: Option<u32>,
+    maxheight: Option<u32>,
+}
+
+/// A `rich` oEmbed response; see https://oembed.com.
+#[derive(Debug, Serialize)]
+pub struct OEmbedResponse {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    version: &'static str,
+    title: String,
+    author_name: String,
+    provider_name: &'static str,
+    provider_url: String,
+    html: String,
+    width: u32,
+    height: u32,
+    thumbnail_url: String,
+}
+
+/// Links for a poll that previews and embeds point at.
+struct PollLinks {
+    page: String,
+    share: String,
+    embed: String,
+    chart: String,
+    oembed: String,
+}
+
+impl PollLinks {
+    fn new(app_state: &AppState, poll_id: &str) -> Self {
+        let config = &app_state.config;
+        let share = format!("{}/p/{}", config.public_url, poll_id);
+        PollLinks {
+            page: format!("{}/polls/{}", config.frontend_url, poll_id),
+            embed: format!("{}/embed/polls/{}", config.public_url, poll_id),
+            chart: format!("{}/api/polls/{}/chart.png", config.public_url, poll_id),
+            oembed: format!("{}/api/oembed?url={}", config.public_url, url::form_urlencoded::byte_serialize(share.as_bytes()).collect::<String>()),
+            share,
+        }
+    }
+}
+
+fn summary(poll: &PollResponse) -> String {
+    format!(
+        "A poll by {} · {} vote{} · {}",
+        poll.creator_display_name,
+        poll.total_votes,
+        if poll.total_votes == 1 { "" } else { "s" },
+        if poll.is_closed { "voting closed" } else { "voting open" },
+    )
+}
+
+async fn load_poll(app_state: &AppState, session: &Session, poll_id: &str) -> Result<PollResponse, WebauthnError> {
+    let (_, poll) = find_viewable_poll(app_state, session, poll_id).await?;
+    poll_response(&app_state.db.collection::<User>("users"), poll).await
+}
+
+/// Pulls the poll ID out of a link to the web app (`/polls/:id`) or to a
+/// share page (`/p/:id`). Links to other sites are rejected.
+fn poll_id_from_url(app_state: &AppState, link: &str) -> Result<String, WebauthnError> {
+    let invalid = || WebauthnError::InvalidInput("URL is not a poll link".into());
+    let link = Url::parse(link).map_err(|_| invalid())?;
+    let ours = [&app_state.config.frontend_url, &app_state.config.public_url].into_iter()
+        .filter_map(|base| Url::parse(base).ok())
+        .any(|base| base.origin() == link.origin());
+    if !ours {
+        return Err(invalid());
+    }
+    let segments: Vec<&str> = link.path_segments().map(|segments| segments.filter(|s| !s.is_empty()).collect()).unwrap_or_default();
+    match segments.as_slice() {
+        ["polls", poll_id] | ["p", poll_id] => Ok(poll_id.to_string()),
+        _ => Err(invalid()),
+    }
+}
+
+/// oEmbed provider endpoint. Only JSON is supported; consumers asking for
+/// XML get 501, as the spec requires.
+pub async fn oembed(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<OEmbedQueryParams>,
+) -> Result<Response, WebauthnError> {
+    if params.format.as_deref().is_some_and(|format| format != "json") {
+        return Ok(StatusCode::NOT_IMPLEMENTED.into_response());
+    }
+    let poll_id = poll_id_from_url(&app_state, &params.url)?;
+    let poll = load_poll(&app_state, &session, &poll_id).await?;
+    let links = PollLinks::new(&app_state, &poll.id);
+
+    let width = params.maxwidth.map_or(EMBED_WIDTH, |max| max.min(EMBED_WIDTH));
+    let natural_height = EMBED_BASE_HEIGHT + EMBED_ROW_HEIGHT * poll.options.len() as u32;
+    let height = params.maxheight.map_or(natural_height, |max| max.min(natural_height));
+    let html = format!(
+        "<iframe src=\"{}\" width=\"{width}\" height=\"{height}\" frameborder=\"0\" title=\"{}\"></iframe>",
+        escape(&links.embed),
+        escape(&poll.title),
+    );
+
+    Ok(Json(OEmbedResponse {
+        kind: "rich",
+        version: "1.0",
+        author_name: poll.creator_display_name,
+        title: poll.title,
+        provider_name: PROVIDER_NAME,
+        provider_url: app_state.config.frontend_url.clone(),
+        html,
+        width,
+        height,
+        thumbnail_url: links.chart,
+    }).into_response())
+}
+
+/// Share page for a poll: Open Graph and Twitter card metadata for link
+/// unfurlers, and a redirect to the web app for everyone else.
+pub async fn share_page(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(poll_id): Path<String>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let poll = load_poll(&app_state, &session, &poll_id).await?;
+    let links = PollLinks::new(&app_state, &poll.id);
+    let title = escape(&poll.title);
+    let description = escape(&summary(&poll));
+    let page = escape(&links.page);
+
+    Ok(Html(format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<meta name="description" content="{description}">
+<meta property="og:type" content="website">
+<meta property="og:site_name" content="{PROVIDER_NAME}">
+<meta property="og:title" content="{title}">
+<meta property="og:description" content="{description}">
+<meta property="og:url" content="{share}">
+<meta property="og:image" content="{chart}">
+<meta name="twitter:card" content="summary_large_image">
+<meta name="twitter:title" content="{title}">
+<meta name="twitter:description" content="{description}">
+<meta name="twitter:image" content="{chart}">
+<link rel="alternate" type="application/json+oembed" href="{oembed}" title="{title}">
+<link rel="canonical" href="{page}">
+<meta http-equiv="refresh" content="0; url={page}">
+</head>
+<body>
+<p><a href="{page}">{title}</a></p>
+</body>
+</html>
+"#,
+        share = escape(&links.share),
+        chart = escape(&links.chart),
+        oembed = escape(&links.oembed),
+    )))
+}
+
+/// Read-only results widget for iframes. Refreshes itself so embedded
+/// results stay current without any script.
+pub async fn embed_widget(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(poll_id): Path<String>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let poll = load_poll(&app_state, &session, &poll_id).await?;
+    let links = PollLinks::new(&app_state, &poll.id);
+
+    let mut rows = String::new();
+    for option in option_statistics(&poll.options, poll.total_votes) {
+        let _ = write!(rows, r#"<div class="row"><div class="label"><span>{}</span><span class="count">{} · {:.1}%</span></div><div class="track"><div class="bar" style="width:{:.2}%"></div></div></div>"#,
+            escape(&option.text), option.votes, option.percentage, option.percentage);
+    }
+
+    Ok(Html(format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="{EMBED_REFRESH_SECS}">
+<title>{title}</title>
+<style>
+body {{ margin: 0; padding: 16px 20px; font-family: system-ui, -apple-system, sans-serif; color: #1f2937; background: white; }}
+h1 {{ font-size: 18px; margin: 0 0 4px; }}
+.meta {{ font-size: 13px; color: #6b7280; margin-bottom: 16px; }}
+.row {{ margin-bottom: 14px; }}
+.label {{ display: flex; justify-content: space-between; font-size: 14px; margin-bottom: 6px; }}
+.count {{ color: #6b7280; }}
+.track {{ background: #f3f4f6; border-radius: 9999px; height: 10px; overflow: hidden; }}
+.bar {{ background: linear-gradient(90deg, #8b5cf6, #ec4899); height: 100%; }}
+a {{ color: #8b5cf6; font-size: 13px; font-weight: 600; text-decoration: none; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<div class="meta">{summary}</div>
+{rows}
+<a href="{page}" target="_blank" rel="noopener">Open poll →</a>
+</body>
+</html>
+"#,
+        title = escape(&poll.title),
+        summary = escape(&summary(&poll)),
+        page = escape(&links.page),
+    )))
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/oembed", get(oembed))
+        .route("/p/:pollId", get(share_page))
+        .route("/embed/polls/:pollId", get(embed_widget))
+}
@@ -1,6 +1,25 @@
-use axum::{http::StatusCode, response::{IntoResponse, Response}};
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
 use thiserror::Error;
 
+use crate::request_id;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        FieldError { field: field.into(), message: message.into() }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum WebauthnError {
     #[error("unknown webauthn error")]
@@ -9,8 +28,20 @@ pub enum WebauthnError {
     CorruptSession,
     #[error("User Not Found")]
     UserNotFound,
-    #[error("User Has No Credentials")]
-    UserHasNoCredentials,
+    #[error("Poll Not Found")]
+    PollNotFound,
+    #[error("Organization Not Found")]
+    OrgNotFound,
+    #[error("Invite Not Found")]
+    InviteNotFound,
+    #[error("API Token Not Found")]
+    TokenNotFound,
+    #[error("Session Not Found")]
+    SessionNotFound,
+    #[error("Webhook Not Found")]
+    WebhookNotFound,
+    #[error("No such endpoint")]
+    NotFound,
     #[error("User Already Exists")]
     UserExists,
     #[error("Invalid Credential")]
@@ -21,24 +52,145 @@ pub enum WebauthnError {
     InvalidSessionState(#[from] tower_sessions::session::Error),
     #[error("User not authenticated")]
     Unauthenticated,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Account Suspended")]
+    AccountSuspended,
+    #[error("Confirm it's you with your passkey to continue")]
+    ReauthenticationRequired,
+    #[error("This passkey may have been copied. Use a recovery code to replace it")]
+    PasskeyPossiblyCloned,
+    #[error("No passkey prompt is in progress. Please start again")]
+    ChallengeMissing,
+    #[error("The passkey prompt timed out. Please start again")]
+    ChallengeExpired,
+    #[error("This passkey prompt was already used. Please start again")]
+    ChallengeAlreadyUsed,
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Too many requests, retry after {retry_after}s")]
+    RateLimited { retry_after: u64 },
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+    #[error("Invalid input: {message}")]
+    InvalidFields { message: String, fields: Vec<FieldError> },
+}
+
+/// RFC 7807 `application/problem+json` body.
+#[derive(Debug, Serialize)]
+struct Problem {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+impl WebauthnError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            WebauthnError::CorruptSession => StatusCode::BAD_REQUEST,
+            WebauthnError::UserNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::PollNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::OrgNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::InviteNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::TokenNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::SessionNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::WebhookNotFound => StatusCode::NOT_FOUND,
+            WebauthnError::NotFound => StatusCode::NOT_FOUND,
+            WebauthnError::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+            WebauthnError::InvalidSessionState(_) => StatusCode::BAD_REQUEST,
+            WebauthnError::UserExists => StatusCode::CONFLICT,
+            WebauthnError::InvalidCredential => StatusCode::BAD_REQUEST,
+            WebauthnError::DatabaseError => StatusCode::INTERNAL_SERVER_ERROR,
+            WebauthnError::Unauthenticated => StatusCode::UNAUTHORIZED,
+            WebauthnError::Forbidden => StatusCode::FORBIDDEN,
+            WebauthnError::AccountSuspended => StatusCode::FORBIDDEN,
+            WebauthnError::ReauthenticationRequired => StatusCode::FORBIDDEN,
+            WebauthnError::PasskeyPossiblyCloned => StatusCode::FORBIDDEN,
+            WebauthnError::ChallengeMissing => StatusCode::BAD_REQUEST,
+            WebauthnError::ChallengeExpired => StatusCode::BAD_REQUEST,
+            WebauthnError::ChallengeAlreadyUsed => StatusCode::CONFLICT,
+            WebauthnError::Conflict(_) => StatusCode::CONFLICT,
+            WebauthnError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            WebauthnError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            WebauthnError::InvalidFields { .. } => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Stable, machine-readable identifier. Clients branch on this, so never
+    /// change an existing value.
+    pub fn code(&self) -> &'static str {
+        match self {
+            WebauthnError::CorruptSession => "corrupt_session",
+            WebauthnError::UserNotFound => "user_not_found",
+            WebauthnError::PollNotFound => "poll_not_found",
+            WebauthnError::OrgNotFound => "org_not_found",
+            WebauthnError::InviteNotFound => "invite_not_found",
+            WebauthnError::TokenNotFound => "token_not_found",
+            WebauthnError::SessionNotFound => "session_not_found",
+            WebauthnError::WebhookNotFound => "webhook_not_found",
+            WebauthnError::NotFound => "not_found",
+            WebauthnError::Unknown => "unknown",
+            WebauthnError::InvalidSessionState(_) => "invalid_session_state",
+            WebauthnError::UserExists => "user_exists",
+            WebauthnError::InvalidCredential => "invalid_credential",
+            WebauthnError::DatabaseError => "database_error",
+            WebauthnError::Unauthenticated => "unauthenticated",
+            WebauthnError::Forbidden => "forbidden",
+            WebauthnError::AccountSuspended => "account_suspended",
+            WebauthnError::ReauthenticationRequired => "reauthentication_required",
+            WebauthnError::PasskeyPossiblyCloned => "passkey_possibly_cloned",
+            WebauthnError::ChallengeMissing => "challenge_missing",
+            WebauthnError::ChallengeExpired => "challenge_expired",
+            WebauthnError::ChallengeAlreadyUsed => "challenge_already_used",
+            WebauthnError::Conflict(_) => "conflict",
+            WebauthnError::RateLimited { .. } => "rate_limited",
+            WebauthnError::InvalidInput(_) => "invalid_input",
+            WebauthnError::InvalidFields { .. } => "invalid_input",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            WebauthnError::Unknown => "Unknown Error".to_string(),
+            // Don't leak session store internals to the client.
+            WebauthnError::InvalidSessionState(_) => "Deserialising Session failed".to_string(),
+            WebauthnError::Forbidden => "You do not have permission to perform this action".to_string(),
+            WebauthnError::Conflict(msg) => msg.clone(),
+            WebauthnError::InvalidInput(msg) => msg.clone(),
+            WebauthnError::InvalidFields { message, .. } => message.clone(),
+            other => other.to_string(),
+        }
+    }
 }
 
 impl IntoResponse for WebauthnError {
     fn into_response(self) -> Response {
-        let (status, body) = match self {
-            WebauthnError::CorruptSession => (StatusCode::BAD_REQUEST, "Corrupt Session".to_string()),
-            WebauthnError::UserNotFound => (StatusCode::NOT_FOUND, "User Not Found".to_string()),
-            WebauthnError::Unknown => (StatusCode::INTERNAL_SERVER_ERROR, "Unknown Error".to_string()),
-            WebauthnError::UserHasNoCredentials => (StatusCode::BAD_REQUEST, "User Has No Credentials".to_string()),
-            WebauthnError::InvalidSessionState(_) => (StatusCode::BAD_REQUEST, "Deserialising Session failed".to_string()),
-            WebauthnError::UserExists => (StatusCode::CONFLICT, "User Already Exists".to_string()),
-            WebauthnError::InvalidCredential => (StatusCode::BAD_REQUEST, "Invalid Credential".to_string()),
-            WebauthnError::DatabaseError => (StatusCode::INTERNAL_SERVER_ERROR, "Database Error".to_string()),
-            WebauthnError::Unauthenticated => (StatusCode::UNAUTHORIZED, "User not authenticated".to_string()),
-            WebauthnError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg),
+        let status = self.status();
+        let problem = Problem {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: self.detail(),
+            code: self.code(),
+            request_id: request_id::current(),
+            errors: match &self {
+                WebauthnError::InvalidFields { fields, .. } => fields.clone(),
+                _ => Vec::new(),
+            },
         };
-        (status, body).into_response()
+
+        let mut response = (status, Json(problem)).into_response();
+        response.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        if let WebauthnError::RateLimited { retry_after } = self {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,279 @@
+use std::io::{Seek, SeekFrom};
+
+use async_stream::stream;
+use axum::{
+    body::{Body, Bytes},
+    extract::Extension,
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::IntoResponse,
+    Router, routing::get,
+};
+use futures::{Stream, TryStreamExt};
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::Cursor;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
+
+use crate::error::WebauthnError;
+use crate::extract::Query;
+use crate::permissions::{authorize, CanExport, Caller, PollAccess, PollAction};
+use crate::polls::{option_statistics, OptionStatistics, Poll, Vote};
+use crate::startup::AppState;
+
+/// How many ballots the XLSX writer may fall behind the database cursor.
+const XLSX_BALLOT_BUFFER: usize = 256;
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQueryParams {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+/// Everything in an export except the ballots, which are streamed after it.
+#[derive(Debug, Serialize)]
+struct ExportHeader {
+    poll_id: String,
+    title: String,
+    round: i32,
+    is_closed: bool,
+    is_anonymous: bool,
+    total_votes: i32,
+    options: Vec<OptionStatistics>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportBallot {
+    user_id: String,
+    option_id: String,
+    voted_at: String,
+}
+
+impl From<Vote> for ExportBallot {
+    fn from(vote: Vote) -> Self {
+        ExportBallot {
+            user_id: vote.user_id.to_string(),
+            option_id: vote.option_id,
+            voted_at: vote.voted_at.to_string(),
+        }
+    }
+}
+
+type ExportChunk = Result<Bytes, std::io::Error>;
+
+/// Downloads the current round's tallies and, to callers who may see them
+/// (`PollAction::ViewBallots`), every ballot. CSV and JSON are written while the ballots are read; XLSX
+/// has to be zipped as a whole, so it goes through a temporary file instead
+/// of memory.
+pub async fn export_poll(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanExport>,
+    Query(params): Query<ExportQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let PollAccess { user_id, poll_id, poll, .. } = access;
+    let caller = Caller::for_poll(&app_state, &poll, user_id).await?;
+    let ballots = if authorize(&poll, &caller, PollAction::ViewBallots).is_err() {
+        None
+    } else {
+        Some(app_state.db.collection::<Vote>("votes")
+            .find(doc! { "poll_id": &poll_id }, None).await
+            .map_err(|e| { error!("Failed to fetch votes: {:?}", e); WebauthnError::DatabaseError })?)
+    };
+    let header = export_header(&poll_id, &poll);
+
+    let body = match params.format {
+        ExportFormat::Csv => Body::from_stream(csv_export(header, ballots)),
+        ExportFormat::Json => Body::from_stream(json_export(header, ballots)),
+        ExportFormat::Xlsx => Body::from_stream(xlsx_export(header, ballots).await?),
+    };
+    info!("Exporting poll {} as {:?}", poll_id, params.format);
+
+    let disposition = format!("attachment; filename=\"poll-{}.{}\"", poll_id.to_hex(), params.format.extension());
+    Ok(([(CONTENT_TYPE, params.format.content_type().to_string()), (CONTENT_DISPOSITION, disposition)], body))
+}
+
+fn export_header(poll_id: &ObjectId, poll: &Poll) -> ExportHeader {
+    ExportHeader {
+        poll_id: poll_id.to_hex(),
+        title: poll.title.clone(),
+        round: poll.round,
+        is_closed: !poll.accepting_votes(),
+        is_anonymous: poll.is_anonymous,
+        total_votes: poll.total_votes,
+        options: option_statistics(&poll.options, poll.total_votes),
+    }
+}
+
+fn cursor_error(e: mongodb::error::Error) -> std::io::Error {
+    error!("Failed to read votes during export: {:?}", e);
+    std::io::Error::other("failed to read votes")
+}
+
+fn csv_row<I, T>(fields: I) -> ExportChunk
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields)?;
+    writer.into_inner().map(Bytes::from).map_err(|e| e.into_error())
+}
+
+/// The tallies table, then a blank line and the ballots table.
+fn csv_export(header: ExportHeader, ballots: Option<Cursor<Vote>>) -> impl Stream<Item = ExportChunk> {
+    stream! {
+        yield csv_row(["option_id", "option_text", "votes", "percentage"]);
+        for option in &header.options {
+            yield csv_row([option.id.clone(), option.text.clone(), option.votes.to_string(), format!("{:.2}", option.percentage)]);
+        }
+        if let Some(mut ballots) = ballots {
+            yield Ok(Bytes::from_static(b"\n"));
+            yield csv_row(["user_id", "option_id", "voted_at"]);
+            loop {
+                match ballots.try_next().await {
+                    Ok(Some(vote)) => {
+                        let ballot = ExportBallot::from(vote);
+                        yield csv_row([ballot.user_id, ballot.option_id, ballot.voted_at]);
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        yield Err(cursor_error(e));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The header object with a `ballots` array spliced in one element at a time.
+fn json_export(header: ExportHeader, ballots: Option<Cursor<Vote>>) -> impl Stream<Item = ExportChunk> {
+    stream! {
+        let mut head = match serde_json::to_vec(&header) {
+            Ok(head) => head,
+            Err(e) => {
+                yield Err(e.into());
+                return;
+            }
+        };
+        let Some(mut ballots) = ballots else {
+            yield Ok(Bytes::from(head));
+            return;
+        };
+
+        // Reopen the object to append the ballots array.
+        head.pop();
+        head.extend_from_slice(b",\"ballots\":[");
+        yield Ok(Bytes::from(head));
+        let mut first = true;
+        loop {
+            match ballots.try_next().await {
+                Ok(Some(vote)) => {
+                    let mut chunk = if first { Vec::new() } else { vec![b','] };
+                    first = false;
+                    if let Err(e) = serde_json::to_writer(&mut chunk, &ExportBallot::from(vote)) {
+                        yield Err(e.into());
+                        return;
+                    }
+                    yield Ok(Bytes::from(chunk));
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    yield Err(cursor_error(e));
+                    return;
+                }
+            }
+        }
+        yield Ok(Bytes::from_static(b"]}"));
+    }
+}
+
+/// Builds the workbook on a blocking thread in constant-memory mode, fed
+/// ballots through a bounded channel, and streams the finished file back.
+async fn xlsx_export(header: ExportHeader, ballots: Option<Cursor<Vote>>) -> Result<ReaderStream<tokio::fs::File>, WebauthnError> {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<ExportBallot>(XLSX_BALLOT_BUFFER);
+    let include_ballots = ballots.is_some();
+    let writer = tokio::task::spawn_blocking(move || -> Result<std::fs::File, XlsxError> {
+        let mut workbook = Workbook::new();
+        let bold = Format::new().set_bold();
+
+        let tallies = workbook.add_worksheet_with_constant_memory().set_name("Results")?;
+        for (col, title) in ["Option ID", "Option", "Votes", "Percentage"].into_iter().enumerate() {
+            tallies.write_string_with_format(0, col as u16, title, &bold)?;
+        }
+        for (row, option) in header.options.iter().enumerate() {
+            let row = row as u32 + 1;
+            tallies.write_string(row, 0, &option.id)?;
+            tallies.write_string(row, 1, &option.text)?;
+            tallies.write_number(row, 2, option.votes)?;
+            tallies.write_number(row, 3, option.percentage)?;
+        }
+
+        if include_ballots {
+            let sheet = workbook.add_worksheet_with_constant_memory().set_name("Ballots")?;
+            for (col, title) in ["User ID", "Option ID", "Voted at"].into_iter().enumerate() {
+                sheet.write_string_with_format(0, col as u16, title, &bold)?;
+            }
+            let mut row = 1;
+            while let Some(ballot) = receiver.blocking_recv() {
+                sheet.write_string(row, 0, ballot.user_id)?;
+                sheet.write_string(row, 1, ballot.option_id)?;
+                sheet.write_string(row, 2, ballot.voted_at)?;
+                row += 1;
+            }
+        }
+
+        let mut file = tempfile::tempfile()?;
+        workbook.save_to_writer(&mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    });
+
+    if let Some(mut ballots) = ballots {
+        while let Some(vote) = ballots.try_next().await
+            .map_err(|e| { error!("Failed to read votes during export: {:?}", e); WebauthnError::DatabaseError })? {
+            if sender.send(vote.into()).await.is_err() {
+                // The writer failed; its error is reported below.
+                break;
+            }
+        }
+    }
+    drop(sender);
+
+    let file = writer.await
+        .map_err(|e| { error!("XLSX export task failed: {:?}", e); WebauthnError::Unknown })?
+        .map_err(|e| { error!("Failed to write XLSX export: {:?}", e); WebauthnError::Unknown })?;
+    Ok(ReaderStream::new(tokio::fs::File::from_std(file)))
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/polls/:pollId/export", get(export_poll))
+}
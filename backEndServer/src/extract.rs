@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequest, FromRequestParts, Request,
+    },
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use mongodb::bson::oid::ObjectId;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::{FieldError, WebauthnError};
+
+// Drop-in replacements for axum's `Json`, `Path` and `Query` whose
+// rejections are `WebauthnError`s, so that a malformed request gets the
+// same problem+json body as any other invalid input rather than axum's
+// plain text.
+
+/// A JSON request body, or a JSON response.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+/// A JSON request body that may be left out. Only an empty body counts as
+/// absent: axum's `Option<Json<T>>` would also quietly treat a malformed
+/// one as missing.
+#[derive(Debug)]
+pub struct OptionalJson<T>(pub Option<T>);
+
+/// Path parameters.
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+/// Query string parameters.
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for Json<T> {
+    type Rejection = WebauthnError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::Json::<T>::from_request(req, state).await {
+            Ok(axum::Json(value)) => Ok(Json(value)),
+            Err(rejection) => Err(json_rejection(rejection)),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for OptionalJson<T> {
+    type Rejection = WebauthnError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = req.into_parts();
+        let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state).await
+            .map_err(|rejection| WebauthnError::InvalidInput(rejection.body_text()))?;
+        if bytes.is_empty() {
+            return Ok(OptionalJson(None));
+        }
+        let Json(value) = Json::from_request(Request::from_parts(parts, Body::from(bytes)), state).await?;
+        Ok(OptionalJson(Some(value)))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[async_trait]
+impl<T: DeserializeOwned + Send, S: Send + Sync> FromRequestParts<S> for Path<T> {
+    type Rejection = WebauthnError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => Ok(Path(value)),
+            Err(PathRejection::FailedToDeserializePathParams(e)) => Err(WebauthnError::InvalidInput(e.body_text())),
+            Err(rejection) => {
+                // Only happens when the extractor is used on a route without parameters.
+                error!("Path extraction failed: {}", rejection.body_text());
+                Err(WebauthnError::Unknown)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T: DeserializeOwned, S: Send + Sync> FromRequestParts<S> for Query<T> {
+    type Rejection = WebauthnError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match axum::extract::Query::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Query(value)) => Ok(Query(value)),
+            Err(QueryRejection::FailedToDeserializeQueryString(e)) => Err(WebauthnError::InvalidInput(e.body_text())),
+            Err(rejection) => Err(WebauthnError::InvalidInput(rejection.body_text())),
+        }
+    }
+}
+
+/// The ObjectId in path parameter `key`; `what` names it in the error.
+pub fn parse_id(params: &HashMap<String, String>, key: &str, what: &str) -> Result<ObjectId, WebauthnError> {
+    params.get(key)
+        .and_then(|id| ObjectId::parse_str(id).ok())
+        .ok_or_else(|| WebauthnError::InvalidInput(format!("Invalid {} ID", what)))
+}
+
+/// A body that parsed as JSON but doesn't fit the request type is reported
+/// against the offending field; anything else is just invalid input.
+fn json_rejection(rejection: JsonRejection) -> WebauthnError {
+    let JsonRejection::JsonDataError(e) = &rejection else {
+        return WebauthnError::InvalidInput(rejection.body_text());
+    };
+    // axum keeps serde's error, with the path to the field, as the source.
+    let Some(data_error) = std::error::Error::source(e)
+        .and_then(std::error::Error::source)
+        .and_then(|source| source.downcast_ref::<serde_path_to_error::Error<serde_json::Error>>())
+    else {
+        return WebauthnError::InvalidInput(rejection.body_text());
+    };
+
+    let message = data_error.inner().to_string();
+    let path = data_error.path().to_string();
+    // A missing field is reported at its parent; point at the field itself.
+    let field = match message.strip_prefix("missing field `").and_then(|rest| rest.split_once('`')) {
+        Some((name, _)) if path == "." => name.to_string(),
+        Some((name, _)) => format!("{}.{}", path, name),
+        None => path,
+    };
+    WebauthnError::InvalidFields {
+        message: "The request body does not match the expected format".into(),
+        fields: vec![FieldError::new(field, message)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, routing::post, Router};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct CreateRequest {
+        title: String,
+        options: Vec<String>,
+    }
+
+    async fn post_body(body: &'static str, content_type: &str) -> (StatusCode, serde_json::Value) {
+        let app = Router::new().route("/", post(|Json(_): Json<CreateRequest>| async { StatusCode::OK }));
+        let req = Request::post("/").header("content-type", content_type).body(Body::from(body)).unwrap();
+        let response = app.oneshot(req).await.unwrap();
+        let status = response.status();
+        assert_eq!(response.headers()["content-type"], "application/problem+json");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn wrong_type_names_the_field() {
+        let (status, problem) = post_body(r#"{"title": "Lunch", "options": [1]}"#, "application/json").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem["code"], "invalid_input");
+        assert_eq!(problem["errors"][0]["field"], "options[0]");
+    }
+
+    #[tokio::test]
+    async fn missing_field_names_the_field() {
+        let (_, problem) = post_body(r#"{"options": []}"#, "application/json").await;
+        assert_eq!(problem["errors"][0]["field"], "title");
+    }
+
+    #[tokio::test]
+    async fn malformed_json_is_invalid_input() {
+        let (status, problem) = post_body(r#"{"title": "#, "application/json").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(problem["code"], "invalid_input");
+        assert!(problem.get("errors").is_none());
+
+        let (status, _) = post_body(r#"{}"#, "text/plain").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    async fn post_optional(body: &'static str) -> StatusCode {
+        let app = Router::new().route("/", post(|OptionalJson(body): OptionalJson<CreateRequest>| async move {
+            if body.is_some() { StatusCode::CREATED } else { StatusCode::OK }
+        }));
+        let req = Request::post("/").header("content-type", "application/json").body(Body::from(body)).unwrap();
+        app.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn optional_body_may_only_be_empty() {
+        assert_eq!(post_optional("").await, StatusCode::OK);
+        assert_eq!(post_optional(r#"{"title": "Lunch", "options": []}"#).await, StatusCode::CREATED);
+        assert_eq!(post_optional(r#"{"title": "#).await, StatusCode::BAD_REQUEST);
+        assert_eq!(post_optional(r#"{"closes_at": "tomorrow"}"#).await, StatusCode::BAD_REQUEST);
+    }
+}
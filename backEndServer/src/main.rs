@@ -1,15 +1,43 @@
-use axum::{http::StatusCode, response::IntoResponse, Router};
+use axum::Router;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_sessions::{cookie::{time::Duration, SameSite}, Expiry, MemoryStore, SessionManagerLayer};
 use tower_http::cors::{CorsLayer};
-use http::header::{HeaderValue, CONTENT_TYPE, ACCEPT, ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, ACCESS_CONTROL_REQUEST_HEADERS};
+use http::header::{HeaderName, HeaderValue, AUTHORIZATION, CONTENT_TYPE, ACCEPT, ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, ACCESS_CONTROL_REQUEST_HEADERS, RETRY_AFTER};
 use http::Method;
 
+mod account;
+mod admin;
+mod api_tokens;
+mod attestation;
+mod audit;
 mod auth;
+mod ceremonies;
+mod chart;
+mod clone_detection;
+mod config;
+mod embed;
 mod error;
+mod export;
+mod extract;
+mod orgs;
+mod permissions;
+mod poll_roles;
 mod polls;
+mod profiles;
+mod rate_limit;
+mod reauth;
+mod recovery;
+mod reports;
+mod request_id;
+mod rounds;
+mod sessions;
 mod startup;
+mod usernames;
+mod webhooks;
 
+use crate::error::WebauthnError;
+use crate::rate_limit::{MemoryRateLimitStore, RateLimitLayer, RateLimiter};
 use crate::startup::AppState;
 #[macro_use]
 extern crate tracing;
@@ -22,12 +50,32 @@ async fn main() {
     tracing_subscriber::fmt::init();
 
     let app_state = AppState::new().await;
+    tokio::spawn(polls::close_expired_polls(app_state.clone()));
+    tokio::spawn(webhooks::run_delivery_worker(app_state.clone()));
     let session_store = MemoryStore::default();
     let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
 
     let app = Router::new()
         .merge(auth::routes())
         .merge(polls::routes())
+        .merge(poll_roles::routes())
+        .merge(orgs::routes())
+        .merge(admin::routes())
+        .merge(reports::routes())
+        .merge(audit::routes())
+        .merge(rounds::routes())
+        .merge(export::routes())
+        .merge(chart::routes())
+        .merge(embed::routes())
+        .merge(webhooks::routes())
+        .merge(api_tokens::routes())
+        .merge(sessions::routes())
+        .merge(reauth::routes())
+        .merge(recovery::routes())
+        .merge(account::routes())
+        .merge(profiles::routes())
+        .layer(RateLimitLayer::new(RateLimiter::new(&app_state.config, Arc::new(MemoryRateLimitStore::default()))))
+        .layer(axum::middleware::from_fn(api_tokens::bearer_auth))
         .layer(axum::Extension(app_state))
         .layer(
             SessionManagerLayer::new(session_store)
@@ -38,16 +86,18 @@ async fn main() {
         )
         .layer(CorsLayer::new()
             .allow_origin("http://localhost:8081".parse::<HeaderValue>().unwrap())
-            .allow_methods(vec![Method::GET, Method::POST, Method::OPTIONS])
-            .allow_headers(vec![CONTENT_TYPE, ACCEPT, ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, ACCESS_CONTROL_REQUEST_HEADERS])
+            .allow_methods(vec![Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+            .allow_headers(vec![CONTENT_TYPE, ACCEPT, ORIGIN, AUTHORIZATION, ACCESS_CONTROL_REQUEST_METHOD, ACCESS_CONTROL_REQUEST_HEADERS])
+            .expose_headers(vec![HeaderName::from_static(request_id::REQUEST_ID_HEADER), RETRY_AFTER])
             .allow_credentials(true))
-        .fallback(handler_404);
+        .fallback(handler_404)
+        .layer(axum::middleware::from_fn(request_id::request_id));
 
     info!("listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await.expect("Unable to spawn tcp listener");
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
-async fn handler_404() -> impl IntoResponse {
-    (StatusCode::NOT_FOUND, "nothing to see here")
+async fn handler_404() -> WebauthnError {
+    WebauthnError::NotFound
 }
\ No newline at end of file
@@ -0,0 +1,450 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{delete, get, post},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+use crate::auth::{is_authenticated, User};
+use crate::error::WebauthnError;
+use crate::extract::{parse_id, Json, Path};
+use crate::profiles;
+use crate::startup::AppState;
+
+const INVITE_TTL_DAYS: i64 = 7;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Organization {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub created_by: ObjectId,
+    pub created_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl OrgRole {
+    fn rank(self) -> u8 {
+        match self {
+            OrgRole::Owner => 2,
+            OrgRole::Admin => 1,
+            OrgRole::Member => 0,
+        }
+    }
+
+    /// Whether this role grants at least the rights of `other`.
+    pub fn at_least(self, other: OrgRole) -> bool {
+        self.rank() >= other.rank()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrgMembership {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub org_id: ObjectId,
+    pub user_id: ObjectId,
+    pub role: OrgRole,
+    pub joined_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OrgInvite {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub org_id: ObjectId,
+    pub user_id: ObjectId,
+    pub role: OrgRole,
+    pub invited_by: ObjectId,
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrgRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteRequest {
+    pub username: String,
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgResponse {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgMemberResponse {
+    pub user_id: String,
+    pub username: String,
+    pub role: OrgRole,
+    pub joined_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InviteResponse {
+    pub id: String,
+    pub org_id: String,
+    pub org_name: String,
+    pub username: String,
+    pub role: OrgRole,
+    pub expires_at: String,
+}
+
+/// The caller's role in `org_id`, or `None` if they are not a member.
+pub async fn member_role(app_state: &AppState, org_id: &ObjectId, user_id: &ObjectId) -> Result<Option<OrgRole>, WebauthnError> {
+    let membership = app_state.db.collection::<OrgMembership>("org_members")
+        .find_one(doc! { "org_id": org_id, "user_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch org membership: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(membership.map(|m| m.role))
+}
+
+/// IDs of every organization the user belongs to.
+pub async fn member_org_ids(app_state: &AppState, user_id: &ObjectId) -> Result<Vec<ObjectId>, WebauthnError> {
+    let mut cursor = app_state.db.collection::<OrgMembership>("org_members")
+        .find(doc! { "user_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch org memberships: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut org_ids = Vec::new();
+    while let Some(membership) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect org memberships: {:?}", e); WebauthnError::DatabaseError })? {
+        org_ids.push(membership.org_id);
+    }
+    Ok(org_ids)
+}
+
+/// Fails with `Forbidden` unless the user holds at least `min` in the org.
+pub async fn require_org_role(app_state: &AppState, org_id: &ObjectId, user_id: &ObjectId, min: OrgRole) -> Result<OrgRole, WebauthnError> {
+    match member_role(app_state, org_id, user_id).await? {
+        Some(role) if role.at_least(min) => Ok(role),
+        _ => Err(WebauthnError::Forbidden),
+    }
+}
+
+async fn find_org(app_state: &AppState, org_id: &ObjectId) -> Result<Organization, WebauthnError> {
+    app_state.db.collection::<Organization>("orgs").find_one(doc! { "_id": org_id }, None).await
+        .map_err(|e| { error!("Failed to fetch organization: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::OrgNotFound)
+}
+
+async fn owner_count(app_state: &AppState, org_id: &ObjectId) -> Result<u64, WebauthnError> {
+    app_state.db.collection::<OrgMembership>("org_members")
+        .count_documents(doc! { "org_id": org_id, "role": "owner" }, None).await
+        .map_err(|e| { error!("Failed to count org owners: {:?}", e); WebauthnError::DatabaseError })
+}
+
+pub async fn create_org(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Json(req): Json<CreateOrgRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(WebauthnError::InvalidInput("Organization name cannot be empty".into()));
+    }
+
+    let org = Organization { id: None, name: name.to_string(), created_by: user_id, created_at: DateTime::now() };
+    let result = app_state.db.collection::<Organization>("orgs").insert_one(org, None).await
+        .map_err(|e| { error!("Failed to insert organization: {:?}", e); WebauthnError::DatabaseError })?;
+    let org_id = result.inserted_id.as_object_id().ok_or(WebauthnError::DatabaseError)?;
+
+    let membership = OrgMembership { id: None, org_id, user_id, role: OrgRole::Owner, joined_at: DateTime::now() };
+    app_state.db.collection::<OrgMembership>("org_members").insert_one(membership, None).await
+        .map_err(|e| { error!("Failed to insert org membership: {:?}", e); WebauthnError::DatabaseError })?;
+
+    info!("Organization created with ID: {}", org_id);
+    Ok((StatusCode::CREATED, Json(doc! { "org_id": org_id.to_string() })))
+}
+
+pub async fn list_my_orgs(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let mut cursor = app_state.db.collection::<OrgMembership>("org_members")
+        .find(doc! { "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch org memberships: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut orgs = Vec::new();
+    while let Some(membership) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect org memberships: {:?}", e); WebauthnError::DatabaseError })? {
+        let org = find_org(&app_state, &membership.org_id).await?;
+        orgs.push(OrgResponse {
+            id: membership.org_id.to_string(),
+            name: org.name,
+            created_at: org.created_at.to_string(),
+            role: membership.role,
+        });
+    }
+    Ok(Json(orgs))
+}
+
+pub async fn get_org(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    let role = require_org_role(&app_state, &org_id, &user_id, OrgRole::Member).await?;
+    let org = find_org(&app_state, &org_id).await?;
+
+    Ok(Json(OrgResponse { id: org_id.to_string(), name: org.name, created_at: org.created_at.to_string(), role }))
+}
+
+pub async fn list_members(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    require_org_role(&app_state, &org_id, &user_id, OrgRole::Member).await?;
+
+    let user_collection = app_state.db.collection::<User>("users");
+    let mut cursor = app_state.db.collection::<OrgMembership>("org_members")
+        .find(doc! { "org_id": &org_id }, None).await
+        .map_err(|e| { error!("Failed to fetch org members: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut members = Vec::new();
+    while let Some(membership) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect org members: {:?}", e); WebauthnError::DatabaseError })? {
+        let username = user_collection.find_one(doc! { "_id": &membership.user_id }, None).await
+            .map_err(|e| { error!("Failed to fetch user: {:?}", e); WebauthnError::DatabaseError })?
+            .map(|user| user.username)
+            .unwrap_or_default();
+        members.push(OrgMemberResponse {
+            user_id: membership.user_id.to_string(),
+            username,
+            role: membership.role,
+            joined_at: membership.joined_at.to_string(),
+        });
+    }
+    Ok(Json(members))
+}
+
+pub async fn invite_member(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+    Json(req): Json<InviteRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    let caller_role = require_org_role(&app_state, &org_id, &user_id, OrgRole::Admin).await?;
+    if !caller_role.at_least(req.role) {
+        return Err(WebauthnError::Forbidden);
+    }
+
+    let invitee = profiles::find_by_username(&app_state, &req.username).await?
+        .ok_or(WebauthnError::UserNotFound)?;
+    let invitee_id = invitee.id.ok_or(WebauthnError::DatabaseError)?;
+    if member_role(&app_state, &org_id, &invitee_id).await?.is_some() {
+        return Err(WebauthnError::Conflict("User is already a member".into()));
+    }
+
+    let now = DateTime::now();
+    let invite = OrgInvite {
+        id: None,
+        org_id,
+        user_id: invitee_id,
+        role: req.role,
+        invited_by: user_id,
+        created_at: now,
+        expires_at: DateTime::from_millis(now.timestamp_millis() + INVITE_TTL_DAYS * 24 * 60 * 60 * 1000),
+    };
+    let result = app_state.db.collection::<OrgInvite>("org_invites").insert_one(invite, None).await
+        .map_err(|e| { error!("Failed to insert org invite: {:?}", e); WebauthnError::DatabaseError })?;
+    let invite_id = result.inserted_id.as_object_id().ok_or(WebauthnError::DatabaseError)?;
+
+    info!("User {} invited to org {} as {:?}", invitee_id, org_id, req.role);
+    Ok((StatusCode::CREATED, Json(doc! { "invite_id": invite_id.to_string() })))
+}
+
+async fn invite_responses(app_state: &AppState, filter: mongodb::bson::Document) -> Result<Vec<InviteResponse>, WebauthnError> {
+    let user_collection = app_state.db.collection::<User>("users");
+    let mut cursor = app_state.db.collection::<OrgInvite>("org_invites").find(filter, None).await
+        .map_err(|e| { error!("Failed to fetch org invites: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut invites = Vec::new();
+    while let Some(invite) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect org invites: {:?}", e); WebauthnError::DatabaseError })? {
+        let org = find_org(app_state, &invite.org_id).await?;
+        let username = user_collection.find_one(doc! { "_id": &invite.user_id }, None).await
+            .map_err(|e| { error!("Failed to fetch user: {:?}", e); WebauthnError::DatabaseError })?
+            .map(|user| user.username)
+            .unwrap_or_default();
+        invites.push(InviteResponse {
+            id: invite.id.map(|id| id.to_string()).unwrap_or_default(),
+            org_id: invite.org_id.to_string(),
+            org_name: org.name,
+            username,
+            role: invite.role,
+            expires_at: invite.expires_at.to_string(),
+        });
+    }
+    Ok(invites)
+}
+
+pub async fn list_org_invites(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    require_org_role(&app_state, &org_id, &user_id, OrgRole::Admin).await?;
+
+    let filter = doc! { "org_id": &org_id, "expires_at": { "$gt": DateTime::now() } };
+    Ok(Json(invite_responses(&app_state, filter).await?))
+}
+
+pub async fn revoke_invite(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    let invite_id = parse_id(&params, "inviteId", "invite")?;
+    require_org_role(&app_state, &org_id, &user_id, OrgRole::Admin).await?;
+
+    let result = app_state.db.collection::<OrgInvite>("org_invites")
+        .delete_one(doc! { "_id": &invite_id, "org_id": &org_id }, None).await
+        .map_err(|e| { error!("Failed to delete org invite: {:?}", e); WebauthnError::DatabaseError })?;
+    if result.deleted_count == 0 {
+        return Err(WebauthnError::InviteNotFound);
+    }
+    Ok(StatusCode::OK)
+}
+
+pub async fn list_my_invites(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let filter = doc! { "user_id": &user_id, "expires_at": { "$gt": DateTime::now() } };
+    Ok(Json(invite_responses(&app_state, filter).await?))
+}
+
+pub async fn accept_invite(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let invite_id = parse_id(&params, "inviteId", "invite")?;
+
+    let invite_collection = app_state.db.collection::<OrgInvite>("org_invites");
+    let invite = invite_collection.find_one_and_delete(doc! { "_id": &invite_id, "user_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch org invite: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::InviteNotFound)?;
+    if invite.expires_at < DateTime::now() {
+        return Err(WebauthnError::InvalidInput("Invite has expired".into()));
+    }
+    if member_role(&app_state, &invite.org_id, &user_id).await?.is_some() {
+        return Err(WebauthnError::Conflict("User is already a member".into()));
+    }
+
+    let membership = OrgMembership { id: None, org_id: invite.org_id, user_id, role: invite.role, joined_at: DateTime::now() };
+    app_state.db.collection::<OrgMembership>("org_members").insert_one(membership, None).await
+        .map_err(|e| { error!("Failed to insert org membership: {:?}", e); WebauthnError::DatabaseError })?;
+
+    info!("User {} joined org {}", user_id, invite.org_id);
+    Ok(StatusCode::OK)
+}
+
+/// Admins manage members and admins; only owners can grant, change or remove ownership.
+pub async fn update_member_role(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+    Json(req): Json<UpdateRoleRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    let target_id = parse_id(&params, "userId", "user")?;
+    let caller_role = require_org_role(&app_state, &org_id, &user_id, OrgRole::Admin).await?;
+
+    let current = member_role(&app_state, &org_id, &target_id).await?.ok_or(WebauthnError::UserNotFound)?;
+    if !caller_role.at_least(current) || !caller_role.at_least(req.role) {
+        return Err(WebauthnError::Forbidden);
+    }
+    if current == OrgRole::Owner && req.role != OrgRole::Owner && owner_count(&app_state, &org_id).await? <= 1 {
+        return Err(WebauthnError::Conflict("An organization needs at least one owner".into()));
+    }
+
+    app_state.db.collection::<OrgMembership>("org_members").update_one(
+        doc! { "org_id": &org_id, "user_id": &target_id },
+        doc! { "$set": { "role": mongodb::bson::to_bson(&req.role).map_err(|_| WebauthnError::Unknown)? } },
+        None,
+    ).await.map_err(|e| { error!("Failed to update org membership: {:?}", e); WebauthnError::DatabaseError })?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes a member. Members may always remove themselves (leave).
+pub async fn remove_member(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let org_id = parse_id(&params, "orgId", "organization")?;
+    let target_id = parse_id(&params, "userId", "user")?;
+
+    let current = member_role(&app_state, &org_id, &target_id).await?.ok_or(WebauthnError::UserNotFound)?;
+    if target_id != user_id {
+        let caller_role = require_org_role(&app_state, &org_id, &user_id, OrgRole::Admin).await?;
+        if !caller_role.at_least(current) {
+            return Err(WebauthnError::Forbidden);
+        }
+    }
+    if current == OrgRole::Owner && owner_count(&app_state, &org_id).await? <= 1 {
+        return Err(WebauthnError::Conflict("An organization needs at least one owner".into()));
+    }
+
+    app_state.db.collection::<OrgMembership>("org_members")
+        .delete_one(doc! { "org_id": &org_id, "user_id": &target_id }, None).await
+        .map_err(|e| { error!("Failed to delete org membership: {:?}", e); WebauthnError::DatabaseError })?;
+
+    info!("User {} removed from org {}", target_id, org_id);
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/orgs", get(list_my_orgs).post(create_org))
+        .route("/api/orgs/invites", get(list_my_invites))
+        .route("/api/orgs/invites/:inviteId/accept", post(accept_invite))
+        .route("/api/orgs/:orgId", get(get_org))
+        .route("/api/orgs/:orgId/members", get(list_members))
+        .route("/api/orgs/:orgId/members/:userId", delete(remove_member))
+        .route("/api/orgs/:orgId/members/:userId/role", post(update_member_role))
+        .route("/api/orgs/:orgId/invites", get(list_org_invites).post(invite_member))
+        .route("/api/orgs/:orgId/invites/:inviteId", delete(revoke_invite))
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use axum::{
+    async_trait,
+    extract::{Extension, FromRequestParts},
+    http::request::Parts,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+use tower_sessions::Session;
+
+use crate::auth::is_authenticated;
+use crate::error::WebauthnError;
+use crate::extract::Path;
+use crate::orgs::{member_role, OrgRole};
+use crate::polls::{Poll, PollRole};
+use crate::startup::AppState;
+
+/// Something a caller can try to do to a poll.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollAction {
+    View,
+    Vote,
+    Close,
+    Reopen,
+    SetDeadline,
+    Reset,
+    ViewBallots,
+    Export,
+    ManageRoles,
+    ManageWebhooks,
+    TransferOwnership,
+    Appeal,
+}
+
+/// Who is acting on a poll, as far as the policy cares.
+pub struct Caller {
+    pub user_id: ObjectId,
+    /// The caller's role in the poll's organization; `None` for polls outside
+    /// an organization or when the caller is not a member.
+    pub org_role: Option<OrgRole>,
+}
+
+impl Caller {
+    pub async fn for_poll(app_state: &AppState, poll: &Poll, user_id: ObjectId) -> Result<Self, WebauthnError> {
+        let org_role = match &poll.org_id {
+            Some(org_id) => member_role(app_state, org_id, &user_id).await?,
+            None => None,
+        };
+        Ok(Caller { user_id, org_role })
+    }
+}
+
+/// The single place that decides whether `caller` may perform `action` on `poll`.
+/// Returns `Forbidden` rather than `Unauthenticated`: the caller is logged in,
+/// they just aren't allowed to do this.
+///
+/// Polls scoped to an organization are off limits to non-members. Hidden
+/// polls can only be seen by role holders and take no votes. Owners, the
+/// creator and co-owners alike, can do everything, transferring ownership
+/// included, except that only the creator can appeal moderation of the
+/// poll. Editors and admins of the poll's organization moderate: they can
+/// close, reopen, change the deadline and reset. Editors can also add
+/// webhooks. Results viewers can only see individual ballots, unless the
+/// poll is anonymous, and export results.
+pub fn authorize(poll: &Poll, caller: &Caller, action: PollAction) -> Result<(), WebauthnError> {
+    let role = poll.role_of(&caller.user_id);
+    let org_admin = caller.org_role.is_some_and(|org_role| org_role.at_least(OrgRole::Admin));
+    let allowed = if poll.org_id.is_some() && caller.org_role.is_none() {
+        false
+    } else {
+        match action {
+            PollAction::View => !poll.is_hidden || role.is_some(),
+            PollAction::Vote => !poll.is_hidden,
+            PollAction::Close | PollAction::Reopen | PollAction::SetDeadline | PollAction::Reset => {
+                org_admin || matches!(role, Some(PollRole::Owner | PollRole::Editor))
+            }
+            PollAction::ViewBallots => role.is_some() && !poll.is_anonymous,
+            PollAction::Export => role.is_some() || org_admin,
+            PollAction::ManageRoles | PollAction::TransferOwnership => role == Some(PollRole::Owner),
+            PollAction::ManageWebhooks => matches!(role, Some(PollRole::Owner | PollRole::Editor)),
+            PollAction::Appeal => poll.creator_id == caller.user_id,
+        }
+    };
+    if allowed {
+        Ok(())
+    } else {
+        info!("User {} may not {:?} poll {:?}", caller.user_id, action, poll.id);
+        Err(WebauthnError::Forbidden)
+    }
+}
+
+/// Read access for endpoints that don't require a login: public polls are
+/// visible to anyone, organization and hidden polls only to logged-in
+/// callers that `authorize` lets through.
+pub async fn authorize_view(app_state: &AppState, session: &Session, poll: &Poll) -> Result<(), WebauthnError> {
+    if poll.org_id.is_none() && !poll.is_hidden {
+        return Ok(());
+    }
+    let user_id = is_authenticated(app_state, session).await?;
+    let caller = Caller::for_poll(app_state, poll, user_id).await?;
+    authorize(poll, &caller, PollAction::View)
+}
+
+/// Loads a poll by the ID in its URL and checks `authorize_view` on it, for
+/// read-only endpoints that don't go through `PollAccess`.
+pub async fn find_viewable_poll(app_state: &AppState, session: &Session, poll_id: &str) -> Result<(ObjectId, Poll), WebauthnError> {
+    let poll_id = ObjectId::parse_str(poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+    let poll = app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": &poll_id }, None).await
+        .map_err(|e| { error!("Failed to fetch poll: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::PollNotFound)?;
+    authorize_view(app_state, session, &poll).await?;
+    Ok((poll_id, poll))
+}
+
+/// Type-level tag naming the action a `PollAccess` extractor checks for.
+pub trait RequiredAction {
+    const ACTION: PollAction;
+}
+
+pub struct CanView;
+pub struct CanVote;
+pub struct CanClose;
+pub struct CanReopen;
+pub struct CanSetDeadline;
+pub struct CanReset;
+pub struct CanViewBallots;
+pub struct CanExport;
+pub struct CanManageRoles;
+pub struct CanTransferOwnership;
+pub struct CanAppeal;
+
+impl RequiredAction for CanView {
+    const ACTION: PollAction = PollAction::View;
+}
+
+impl RequiredAction for CanVote {
+    const ACTION: PollAction = PollAction::Vote;
+}
+
+impl RequiredAction for CanClose {
+    const ACTION: PollAction = PollAction::Close;
+}
+
+impl RequiredAction for CanReopen {
+    const ACTION: PollAction = PollAction::Reopen;
+}
+
+impl RequiredAction for CanSetDeadline {
+    const ACTION: PollAction = PollAction::SetDeadline;
+}
+
+impl RequiredAction for CanReset {
+    const ACTION: PollAction = PollAction::Reset;
+}
+
+impl RequiredAction for CanViewBallots {
+    const ACTION: PollAction = PollAction::ViewBallots;
+}
+
+impl RequiredAction for CanExport {
+    const ACTION: PollAction = PollAction::Export;
+}
+
+impl RequiredAction for CanManageRoles {
+    const ACTION: PollAction = PollAction::ManageRoles;
+}
+
+impl RequiredAction for CanTransferOwnership {
+    const ACTION: PollAction = PollAction::TransferOwnership;
+}
+
+impl RequiredAction for CanAppeal {
+    const ACTION: PollAction = PollAction::Appeal;
+}
+
+/// Extracts the authenticated caller and the `:pollId` poll, and rejects the
+/// request unless the caller is allowed to perform `A::ACTION` on it.
+pub struct PollAccess<A> {
+    pub user_id: ObjectId,
+    pub poll_id: ObjectId,
+    pub poll: Poll,
+    action: PhantomData<A>,
+}
+
+#[async_trait]
+impl<S, A> FromRequestParts<S> for PollAccess<A>
+where
+    S: Send + Sync,
+    A: RequiredAction,
+{
+    type Rejection = WebauthnError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(app_state) = Extension::<AppState>::from_request_parts(parts, state).await
+            .map_err(|e| { error!("AppState missing from request: {:?}", e); WebauthnError::Unknown })?;
+        let session = Session::from_request_parts(parts, state).await
+            .map_err(|e| { error!("Session missing from request: {:?}", e); WebauthnError::CorruptSession })?;
+        let user_id = is_authenticated(&app_state, &session).await?;
+
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state).await
+            .map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+        let poll_id = params.get("pollId")
+            .and_then(|id| ObjectId::parse_str(id).ok())
+            .ok_or_else(|| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+
+        let poll = app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": &poll_id }, None).await
+            .map_err(|e| { error!("Failed to fetch poll: {:?}", e); WebauthnError::DatabaseError })?
+            .ok_or(WebauthnError::PollNotFound)?;
+
+        let caller = Caller::for_poll(&app_state, &poll, user_id).await?;
+        authorize(&poll, &caller, A::ACTION)?;
+
+        Ok(PollAccess { user_id, poll_id, poll, action: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::DateTime;
+
+    use super::*;
+    use crate::polls::PollMember;
+
+    fn poll(creator_id: ObjectId, members: Vec<PollMember>) -> Poll {
+        Poll {
+            id: Some(ObjectId::new()),
+            title: "Lunch".into(),
+            options: Vec::new(),
+            creator_id,
+            created_at: DateTime::now(),
+            is_closed: false,
+            total_votes: 0,
+            members,
+            org_id: None,
+            is_hidden: false,
+            round: 0,
+            closes_at: None,
+            status_history: Vec::new(),
+            is_anonymous: false,
+        }
+    }
+
+    fn caller(user_id: ObjectId) -> Caller {
+        Caller { user_id, org_role: None }
+    }
+
+    #[test]
+    fn owners_can_transfer_ownership() {
+        let (creator, co_owner, editor) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let poll = poll(creator, vec![
+            PollMember { user_id: co_owner, role: PollRole::Owner },
+            PollMember { user_id: editor, role: PollRole::Editor },
+        ]);
+        assert!(authorize(&poll, &caller(creator), PollAction::TransferOwnership).is_ok());
+        assert!(authorize(&poll, &caller(co_owner), PollAction::TransferOwnership).is_ok());
+        assert!(authorize(&poll, &caller(editor), PollAction::TransferOwnership).is_err());
+        assert!(authorize(&poll, &caller(ObjectId::new()), PollAction::TransferOwnership).is_err());
+    }
+
+    #[test]
+    fn only_the_creator_can_appeal() {
+        let (creator, co_owner) = (ObjectId::new(), ObjectId::new());
+        let poll = poll(creator, vec![PollMember { user_id: co_owner, role: PollRole::Owner }]);
+        assert!(authorize(&poll, &caller(creator), PollAction::Appeal).is_ok());
+        assert!(authorize(&poll, &caller(co_owner), PollAction::Appeal).is_err());
+    }
+
+    #[test]
+    fn org_admins_export_without_ballots() {
+        let mut poll = poll(ObjectId::new(), Vec::new());
+        poll.org_id = Some(ObjectId::new());
+        let admin = Caller { user_id: ObjectId::new(), org_role: Some(OrgRole::Admin) };
+        assert!(authorize(&poll, &admin, PollAction::Export).is_ok());
+        assert!(authorize(&poll, &admin, PollAction::ViewBallots).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{delete, get, post},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, to_bson};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::auth::User;
+use crate::error::WebauthnError;
+use crate::extract::{Json, Path};
+use crate::permissions::{CanManageRoles, CanTransferOwnership, CanViewBallots, PollAccess};
+use crate::polls::{Poll, PollMember, PollRole, Vote};
+use crate::profiles;
+use crate::startup::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct AddMemberRequest {
+    pub username: String,
+    pub role: PollRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferOwnershipRequest {
+    pub username: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemberResponse {
+    pub user_id: String,
+    pub username: String,
+    pub role: PollRole,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BallotResponse {
+    pub user_id: String,
+    pub username: String,
+    pub option_id: String,
+    pub voted_at: String,
+}
+
+async fn find_user_by_username(app_state: &AppState, username: &str) -> Result<User, WebauthnError> {
+    profiles::find_by_username(app_state, username).await?
+        .ok_or(WebauthnError::UserNotFound)
+}
+
+async fn usernames(app_state: &AppState, user_ids: Vec<ObjectId>) -> Result<HashMap<ObjectId, String>, WebauthnError> {
+    let mut cursor = app_state.db.collection::<User>("users")
+        .find(doc! { "_id": { "$in": user_ids } }, None).await
+        .map_err(|e| { error!("Failed to fetch users: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut names = HashMap::new();
+    while let Some(user) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect users: {:?}", e); WebauthnError::DatabaseError })? {
+        if let Some(id) = user.id {
+            names.insert(id, user.username);
+        }
+    }
+    Ok(names)
+}
+
+async fn save_members(app_state: &AppState, poll_id: &ObjectId, members: &[PollMember]) -> Result<(), WebauthnError> {
+    let members = to_bson(members)
+        .map_err(|e| { error!("Failed to serialise poll members: {:?}", e); WebauthnError::Unknown })?;
+    app_state.db.collection::<Poll>("polls").update_one(
+        doc! { "_id": poll_id },
+        doc! { "$set": { "members": members } },
+        None,
+    ).await.map_err(|e| { error!("Failed to update poll members: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+pub async fn list_members(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanManageRoles>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let poll = access.poll;
+    let mut user_ids: Vec<ObjectId> = poll.members.iter().map(|member| member.user_id).collect();
+    user_ids.push(poll.creator_id);
+    let names = usernames(&app_state, user_ids).await?;
+
+    let name_of = |id: &ObjectId| names.get(id).cloned().unwrap_or_default();
+    let mut members = vec![MemberResponse {
+        user_id: poll.creator_id.to_string(),
+        username: name_of(&poll.creator_id),
+        role: PollRole::Owner,
+    }];
+    members.extend(poll.members.iter().map(|member| MemberResponse {
+        user_id: member.user_id.to_string(),
+        username: name_of(&member.user_id),
+        role: member.role,
+    }));
+    Ok(Json(members))
+}
+
+/// Grants `role` to a user, replacing any role they already had.
+pub async fn add_member(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanManageRoles>,
+    Json(req): Json<AddMemberRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user = find_user_by_username(&app_state, &req.username).await?;
+    let user_id = user.id.ok_or(WebauthnError::DatabaseError)?;
+    if user_id == access.poll.creator_id {
+        return Err(WebauthnError::Conflict("The poll creator is already an owner".into()));
+    }
+
+    let mut members = access.poll.members;
+    members.retain(|member| member.user_id != user_id);
+    members.push(PollMember { user_id, role: req.role });
+    save_members(&app_state, &access.poll_id, &members).await?;
+
+    info!("User {} granted {:?} on poll {}", user_id, req.role, access.poll_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn remove_member(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanManageRoles>,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = params.get("userId")
+        .and_then(|id| ObjectId::parse_str(id).ok())
+        .ok_or_else(|| WebauthnError::InvalidInput("Invalid user ID".into()))?;
+    if user_id == access.poll.creator_id {
+        return Err(WebauthnError::Conflict("Transfer ownership before removing the poll creator".into()));
+    }
+
+    let mut members = access.poll.members;
+    let before = members.len();
+    members.retain(|member| member.user_id != user_id);
+    if members.len() == before {
+        return Err(WebauthnError::UserNotFound);
+    }
+    save_members(&app_state, &access.poll_id, &members).await?;
+
+    info!("User {} removed from poll {}", user_id, access.poll_id);
+    Ok(StatusCode::OK)
+}
+
+/// Makes another user the poll creator. The previous creator stays on as a co-owner.
+pub async fn transfer_ownership(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanTransferOwnership>,
+    Json(req): Json<TransferOwnershipRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user = find_user_by_username(&app_state, &req.username).await?;
+    let new_owner = user.id.ok_or(WebauthnError::DatabaseError)?;
+    if new_owner == access.poll.creator_id {
+        return Err(WebauthnError::Conflict("User already owns this poll".into()));
+    }
+
+    let mut members = access.poll.members;
+    members.retain(|member| member.user_id != new_owner);
+    members.push(PollMember { user_id: access.poll.creator_id, role: PollRole::Owner });
+    let members = to_bson(&members)
+        .map_err(|e| { error!("Failed to serialise poll members: {:?}", e); WebauthnError::Unknown })?;
+
+    app_state.db.collection::<Poll>("polls").update_one(
+        doc! { "_id": &access.poll_id },
+        doc! { "$set": { "creator_id": new_owner, "members": members } },
+        None,
+    ).await.map_err(|e| { error!("Failed to transfer poll ownership: {:?}", e); WebauthnError::DatabaseError })?;
+
+    info!("Poll {} transferred from {} to {} by {}", access.poll_id, access.poll.creator_id, new_owner, access.user_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn list_ballots(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanViewBallots>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let mut cursor = app_state.db.collection::<Vote>("votes")
+        .find(doc! { "poll_id": &access.poll_id }, None).await
+        .map_err(|e| { error!("Failed to fetch votes: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut votes = Vec::new();
+    while let Some(vote) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect votes: {:?}", e); WebauthnError::DatabaseError })? {
+        votes.push(vote);
+    }
+
+    let names = usernames(&app_state, votes.iter().map(|vote| vote.user_id).collect()).await?;
+    let ballots: Vec<BallotResponse> = votes.into_iter().map(|vote| BallotResponse {
+        user_id: vote.user_id.to_string(),
+        username: names.get(&vote.user_id).cloned().unwrap_or_default(),
+        option_id: vote.option_id,
+        voted_at: vote.voted_at.to_string(),
+    }).collect();
+    Ok(Json(ballots))
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/polls/:pollId/members", get(list_members).post(add_member))
+        .route("/api/polls/:pollId/members/:userId", delete(remove_member))
+        .route("/api/polls/:pollId/transfer", post(transfer_ownership))
+        .route("/api/polls/:pollId/ballots", get(list_ballots))
+}
@@ -1,21 +1,30 @@
 use axum::{
-    extract::{Extension, Json, Path, Query},
+    extract::Extension,
     http::StatusCode,
     response::{IntoResponse, Sse},
     Router, routing::{get, post},
 };
 use futures::TryStreamExt;
-use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime, Document};
+use mongodb::{options::FindOptions, Collection};
 use serde::{Deserialize, Serialize};
 use tower_sessions::Session;
 use std::time::Duration;
 use async_stream::stream;
 use chrono::Utc;
-use serde_json;
+use serde_json::json;
 
+use crate::account::DELETED_USERNAME;
+use crate::audit::{self, AuditAction, RequestContext};
 use crate::auth::{is_authenticated, User}; // Import User from auth module
-use crate::error::WebauthnError;
+use crate::error::{FieldError, WebauthnError};
+use crate::extract::{Json, OptionalJson, Path, Query};
+use crate::orgs::{member_org_ids, require_org_role, OrgRole};
+use crate::permissions::{authorize_view, CanClose, CanReopen, CanReset, CanSetDeadline, CanVote, PollAccess};
+use crate::reauth::RecentlyVerified;
+use crate::rounds;
 use crate::startup::AppState;
+use crate::webhooks::{self, WebhookEvent};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PollOption {
@@ -34,6 +43,76 @@ pub struct Poll {
     pub created_at: DateTime,
     pub is_closed: bool,
     pub total_votes: i32,
+    /// Users other than the creator who hold a role on this poll.
+    #[serde(default)]
+    pub members: Vec<PollMember>,
+    /// Organization the poll is scoped to; only its members can see or vote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<ObjectId>,
+    /// Set when a poll collects too many reports or a moderator upholds one.
+    #[serde(default)]
+    pub is_hidden: bool,
+    /// Number of times the poll has been reset; see `rounds`.
+    #[serde(default)]
+    pub round: i32,
+    /// When set, voting stops at this time even if nobody closes the poll.
+    #[serde(default)]
+    pub closes_at: Option<DateTime>,
+    /// Every time the poll was opened or closed, oldest first.
+    #[serde(default)]
+    pub status_history: Vec<PollTransition>,
+    /// Anonymous polls never reveal who voted for what, not even to role holders.
+    #[serde(default)]
+    pub is_anonymous: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PollState {
+    Open,
+    Closed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PollTransition {
+    pub state: PollState,
+    pub at: DateTime,
+    /// `None` when the poll closed because its deadline passed.
+    pub by: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PollRole {
+    Owner,
+    Editor,
+    ResultsViewer,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PollMember {
+    pub user_id: ObjectId,
+    pub role: PollRole,
+}
+
+impl Poll {
+    /// The creator is always an owner, whether or not they appear in `members`.
+    pub fn role_of(&self, user_id: &ObjectId) -> Option<PollRole> {
+        if self.creator_id == *user_id {
+            return Some(PollRole::Owner);
+        }
+        self.members.iter().find(|member| member.user_id == *user_id).map(|member| member.role)
+    }
+
+    pub fn deadline_passed(&self) -> bool {
+        self.closes_at.is_some_and(|closes_at| closes_at <= DateTime::now())
+    }
+
+    /// Closed polls and polls past their deadline take no votes, whether or
+    /// not the deadline sweep has caught up yet.
+    pub fn accepting_votes(&self) -> bool {
+        !self.is_closed && !self.deadline_passed()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -44,12 +123,32 @@ pub struct Vote {
     pub user_id: ObjectId,
     pub option_id: String,
     pub voted_at: DateTime,
+    /// The poll's `round` when the vote was counted. Older votes lack it;
+    /// they are always from the current round, as a reset archives them.
+    #[serde(default)]
+    pub round: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreatePollRequest {
     pub title: String,
     pub options: Vec<String>,
+    #[serde(default)]
+    pub org_id: Option<String>,
+    #[serde(default)]
+    pub closes_at: Option<String>,
+    #[serde(default)]
+    pub duration_secs: Option<i64>,
+    #[serde(default)]
+    pub is_anonymous: bool,
+}
+
+/// A new deadline, either as an RFC 3339 time or as seconds from now.
+/// Neither means no deadline.
+#[derive(Debug, Deserialize)]
+pub struct DeadlineRequest {
+    pub closes_at: Option<String>,
+    pub duration_secs: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -64,9 +163,21 @@ pub struct PollResponse {
     pub options: Vec<PollOption>,
     pub creator_id: String,
     pub creator_username: String, // Included as per previous update
+    pub creator_display_name: String,
     pub created_at: String,
     pub is_closed: bool,
     pub total_votes: i32,
+    pub org_id: Option<String>,
+    pub closes_at: Option<String>,
+    pub status_history: Vec<PollTransitionResponse>,
+    pub is_anonymous: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PollTransitionResponse {
+    pub state: PollState,
+    pub at: String,
+    pub by: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -85,83 +196,234 @@ pub struct OptionStatistics {
     pub percentage: f64,
 }
 
+pub fn option_statistics(options: &[PollOption], total_votes: i32) -> Vec<OptionStatistics> {
+    options.iter().map(|opt| OptionStatistics {
+        id: opt.id.clone(),
+        text: opt.text.clone(),
+        votes: opt.votes,
+        percentage: if total_votes > 0 { (opt.votes as f64 / total_votes as f64) * 100.0 } else { 0.0 },
+    }).collect()
+}
+
+const SEARCH_RESULT_LIMIT: i64 = 50;
+const DEADLINE_SWEEP_SECS: u64 = 30;
+
 #[derive(Debug, Deserialize)]
 pub struct PollQueryParams {
     creator: Option<String>,
     closed: Option<bool>,
+    org: Option<String>,
 }
 
-pub async fn get_polls(
-    Extension(app_state): Extension<AppState>,
-    session: Session,
-    Query(params): Query<PollQueryParams>,
-) -> Result<impl IntoResponse, WebauthnError> {
-    let poll_collection = app_state.db.collection::<Poll>("polls");
-    let user_collection = app_state.db.collection::<User>("users");
+#[derive(Debug, Deserialize)]
+pub struct SearchQueryParams {
+    q: String,
+}
 
+/// Which organizations' polls a listing may include.
+#[derive(Debug)]
+enum OrgScope {
+    /// One organization, whose membership has been checked.
+    One(ObjectId),
+    /// Polls outside any organization, plus those of these organizations.
+    Visible(Vec<ObjectId>),
+}
+
+/// Builds the Mongo filter shared by every endpoint that lists polls,
+/// including the visibility rules: organization polls are only listed for
+/// members of that organization, and hidden polls only for their creator.
+async fn poll_filter(app_state: &AppState, session: &Session, params: PollQueryParams) -> Result<Document, WebauthnError> {
+    let org_id = params.org.as_deref().map(ObjectId::parse_str).transpose()
+        .map_err(|_| WebauthnError::InvalidInput("Invalid organization ID".into()))?;
+    // Listing an organization or one's own polls needs a login; otherwise
+    // a visitor just sees less.
+    let viewer = if org_id.is_some() || params.creator.as_deref() == Some("me") {
+        Some(is_authenticated(app_state, session).await?)
+    } else {
+        is_authenticated(app_state, session).await.ok()
+    };
+    let scope = match (org_id, &viewer) {
+        (Some(org_id), Some(user_id)) => {
+            require_org_role(app_state, &org_id, user_id, OrgRole::Member).await?;
+            OrgScope::One(org_id)
+        }
+        (_, Some(user_id)) => OrgScope::Visible(member_org_ids(app_state, user_id).await?),
+        (_, None) => OrgScope::Visible(Vec::new()),
+    };
+    build_poll_filter(scope, viewer, params.creator, params.closed)
+}
+
+/// The part of `poll_filter` that needs no database: the filter for
+/// `viewer` once the organizations they may see are known.
+fn build_poll_filter(
+    scope: OrgScope,
+    viewer: Option<ObjectId>,
+    creator: Option<String>,
+    closed: Option<bool>,
+) -> Result<Document, WebauthnError> {
     let mut filter = doc! {};
-    if let Some(creator) = params.creator {
+    let mut own_polls = false;
+    match scope {
+        OrgScope::One(org_id) => {
+            filter.insert("org_id", org_id);
+        }
+        OrgScope::Visible(org_ids) => {
+            filter.insert("$or", vec![
+                doc! { "org_id": null },
+                doc! { "org_id": { "$in": org_ids } },
+            ]);
+        }
+    }
+    if let Some(creator) = creator {
         if creator == "me" {
-            let user_id = is_authenticated(&session).await?;
-            filter.insert("creator_id", user_id);
+            filter.insert("creator_id", viewer.ok_or(WebauthnError::Unauthenticated)?);
+            own_polls = true;
         } else {
             filter.insert("creator_id", ObjectId::parse_str(creator)
                 .map_err(|_| WebauthnError::InvalidInput("Invalid creator ID".into()))?);
         }
     }
-    if let Some(closed) = params.closed {
+    if let Some(closed) = closed {
         filter.insert("is_closed", closed);
     }
+    if !own_polls {
+        filter.insert("is_hidden", doc! { "$ne": true });
+    }
+    Ok(filter)
+}
+
+/// Narrows a `poll_filter` to a full-text query, ranked by Mongo's text
+/// score. Needs the text index created in `startup`.
+fn text_search(mut filter: Document, query: &str) -> Result<(Document, FindOptions), WebauthnError> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(WebauthnError::InvalidInput("Search query cannot be empty".into()));
+    }
+    filter.insert("$text", doc! { "$search": query });
+    let options = FindOptions::builder()
+        .projection(doc! { "score": { "$meta": "textScore" } })
+        .sort(doc! { "score": { "$meta": "textScore" } })
+        .limit(SEARCH_RESULT_LIMIT)
+        .build();
+    Ok((filter, options))
+}
 
-    let mut cursor = poll_collection.find(filter, None).await
+pub async fn poll_response(user_collection: &Collection<User>, poll: Poll) -> Result<PollResponse, WebauthnError> {
+    // The creator may have deleted their account; see `account`.
+    let (creator_username, creator_display_name) = user_collection.find_one(doc! { "_id": &poll.creator_id }, None).await
+        .map_err(|e| { error!("Failed to fetch user: {:?}", e); WebauthnError::DatabaseError })?
+        .map(|creator| (creator.username.clone(), creator.display_name().to_string()))
+        .unwrap_or_else(|| (DELETED_USERNAME.to_string(), DELETED_USERNAME.to_string()));
+    let is_closed = !poll.accepting_votes();
+
+    Ok(PollResponse {
+        id: poll.id.unwrap().to_string(),
+        title: poll.title,
+        options: poll.options,
+        creator_id: poll.creator_id.to_string(),
+        creator_username,
+        creator_display_name,
+        created_at: poll.created_at.to_string(),
+        is_closed,
+        total_votes: poll.total_votes,
+        org_id: poll.org_id.map(|id| id.to_string()),
+        closes_at: poll.closes_at.map(|closes_at| closes_at.to_string()),
+        status_history: poll.status_history.into_iter().map(|transition| PollTransitionResponse {
+            state: transition.state,
+            at: transition.at.to_string(),
+            by: transition.by.map(|id| id.to_string()),
+        }).collect(),
+        is_anonymous: poll.is_anonymous,
+    })
+}
+
+pub async fn find_poll_responses(
+    app_state: &AppState,
+    filter: Document,
+    options: Option<FindOptions>,
+) -> Result<Vec<PollResponse>, WebauthnError> {
+    let poll_collection = app_state.db.collection::<Poll>("polls");
+    let user_collection = app_state.db.collection::<User>("users");
+
+    let mut cursor = poll_collection.find(filter, options).await
         .map_err(|e| { error!("Failed to fetch polls: {:?}", e); WebauthnError::DatabaseError })?;
 
     let mut poll_responses = Vec::new();
     while let Some(poll) = cursor.try_next().await
         .map_err(|e| { error!("Failed to collect polls: {:?}", e); WebauthnError::DatabaseError })? {
-        let creator = user_collection.find_one(doc! { "_id": &poll.creator_id }, None).await
-            .map_err(|e| { error!("Failed to fetch user: {:?}", e); WebauthnError::DatabaseError })?
-            .ok_or_else(|| { error!("Creator not found for poll: {:?}", poll.id); WebauthnError::UserNotFound })?;
-
-        poll_responses.push(PollResponse {
-            id: poll.id.unwrap().to_string(),
-            title: poll.title,
-            options: poll.options,
-            creator_id: poll.creator_id.to_string(),
-            creator_username: creator.username, // Now included
-            created_at: poll.created_at.to_string(),
-            is_closed: poll.is_closed,
-            total_votes: poll.total_votes,
-        });
+        poll_responses.push(poll_response(&user_collection, poll).await?);
     }
+    Ok(poll_responses)
+}
+
+pub async fn get_polls(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<PollQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let filter = poll_filter(&app_state, &session, params).await?;
+    Ok(Json(find_poll_responses(&app_state, filter, None).await?))
+}
+
+/// Full-text search over poll titles and option texts, most relevant first.
+/// Accepts the same `creator` and `closed` filters as `get_polls`.
+pub async fn search_polls(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<PollQueryParams>,
+    Query(search): Query<SearchQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let filter = poll_filter(&app_state, &session, params).await?;
+    let (filter, options) = text_search(filter, &search.q)?;
 
-    Ok(Json(poll_responses))
+    Ok(Json(find_poll_responses(&app_state, filter, Some(options)).await?))
 }
 
 pub async fn create_poll(
     Extension(app_state): Extension<AppState>,
     session: Session,
+    ctx: RequestContext,
     Json(poll_req): Json<CreatePollRequest>,
 ) -> Result<impl IntoResponse, WebauthnError> {
-    let user_id = is_authenticated(&session).await?;
+    let user_id = is_authenticated(&app_state, &session).await?;
 
+    let mut field_errors = Vec::new();
     if poll_req.title.trim().is_empty() {
-        return Err(WebauthnError::InvalidInput("Poll title cannot be empty".into()));
+        field_errors.push(FieldError::new("title", "Poll title cannot be empty"));
     }
     if poll_req.options.len() < 2 {
-        return Err(WebauthnError::InvalidInput("Poll must have at least 2 options".into()));
+        field_errors.push(FieldError::new("options", "Poll must have at least 2 options"));
     }
 
     let mut unique_options = std::collections::HashSet::new();
-    for option in &poll_req.options {
+    for (index, option) in poll_req.options.iter().enumerate() {
         if option.trim().is_empty() {
-            return Err(WebauthnError::InvalidInput("Poll options cannot be empty".into()));
+            field_errors.push(FieldError::new(format!("options[{}]", index), "Poll options cannot be empty"));
+        } else if !unique_options.insert(option.trim().to_lowercase()) {
+            field_errors.push(FieldError::new(format!("options[{}]", index), "Duplicate poll options not allowed"));
         }
-        if !unique_options.insert(option.trim().to_lowercase()) {
-            return Err(WebauthnError::InvalidInput("Duplicate poll options not allowed".into()));
+    }
+    let closes_at = match parse_deadline(poll_req.closes_at.as_deref(), poll_req.duration_secs) {
+        Ok(closes_at) => closes_at,
+        Err(message) => {
+            field_errors.push(FieldError::new("closes_at", message));
+            None
         }
+    };
+    if !field_errors.is_empty() {
+        return Err(WebauthnError::InvalidFields { message: "Poll is invalid".into(), fields: field_errors });
     }
 
+    let org_id = match &poll_req.org_id {
+        Some(org) => {
+            let org_id = ObjectId::parse_str(org)
+                .map_err(|_| WebauthnError::InvalidInput("Invalid organization ID".into()))?;
+            require_org_role(&app_state, &org_id, &user_id, OrgRole::Member).await?;
+            Some(org_id)
+        }
+        None => None,
+    };
+
     let options: Vec<PollOption> = poll_req.options.iter().map(|text| PollOption {
         id: uuid::Uuid::new_v4().to_string(),
         text: text.clone(),
@@ -176,19 +438,34 @@ pub async fn create_poll(
         created_at: DateTime::now(),
         is_closed: false,
         total_votes: 0,
+        members: Vec::new(),
+        org_id,
+        is_hidden: false,
+        round: 0,
+        closes_at,
+        status_history: vec![PollTransition { state: PollState::Open, at: DateTime::now(), by: Some(user_id) }],
+        is_anonymous: poll_req.is_anonymous,
     };
 
     let poll_collection = app_state.db.collection::<Poll>("polls");
-    let result = poll_collection.insert_one(poll, None).await
+    let result = poll_collection.insert_one(&poll, None).await
         .map_err(|e| { error!("Failed to insert poll: {:?}", e); WebauthnError::DatabaseError })?;
 
     let poll_id = result.inserted_id.as_object_id().ok_or(WebauthnError::DatabaseError)?;
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::PollCreated, Some(poll_id.to_hex())).await;
+    let options: Vec<_> = poll.options.iter().map(|option| json!({ "id": option.id, "text": option.text })).collect();
+    webhooks::dispatch(&app_state, WebhookEvent::PollCreated, &poll_id, &poll, json!({
+        "options": options,
+        "closes_at": poll.closes_at.map(|closes_at| closes_at.to_string()),
+        "is_anonymous": poll.is_anonymous,
+    })).await;
     info!("Poll created with ID: {}", poll_id);
     Ok((StatusCode::CREATED, Json(doc! { "poll_id": poll_id.to_string() })))
 }
 
 pub async fn get_poll(
     Extension(app_state): Extension<AppState>,
+    session: Session,
     Path(poll_id): Path<String>,
 ) -> Result<impl IntoResponse, WebauthnError> {
     let poll_id = ObjectId::parse_str(&poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
@@ -197,140 +474,267 @@ pub async fn get_poll(
 
     let poll = poll_collection.find_one(doc! { "_id": poll_id }, None).await
         .map_err(|_| WebauthnError::DatabaseError)?
-        .ok_or(WebauthnError::UserNotFound)?;
+        .ok_or(WebauthnError::PollNotFound)?;
+    authorize_view(&app_state, &session, &poll).await?;
 
-    let creator = user_collection.find_one(doc! { "_id": &poll.creator_id }, None).await
-        .map_err(|e| { error!("Failed to fetch user: {:?}", e); WebauthnError::DatabaseError })?
-        .ok_or_else(|| { error!("Creator not found for poll: {:?}", poll.id); WebauthnError::UserNotFound })?;
-
-    Ok(Json(PollResponse {
-        id: poll.id.unwrap().to_string(),
-        title: poll.title,
-        options: poll.options,
-        creator_id: poll.creator_id.to_string(),
-        creator_username: creator.username,
-        created_at: poll.created_at.to_string(),
-        is_closed: poll.is_closed,
-        total_votes: poll.total_votes,
-    }))
+    Ok(Json(poll_response(&user_collection, poll).await?))
 }
 
 pub async fn vote_poll(
     Extension(app_state): Extension<AppState>,
-    session: Session,
-    Path(poll_id): Path<String>,
+    access: PollAccess<CanVote>,
     Json(vote_req): Json<VoteRequest>,
 ) -> Result<impl IntoResponse, WebauthnError> {
-    let user_id = is_authenticated(&session).await?;
-    let poll_id = ObjectId::parse_str(&poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+    let PollAccess { user_id, poll_id, poll, .. } = access;
 
     let poll_collection = app_state.db.collection::<Poll>("polls");
     let vote_collection = app_state.db.collection::<Vote>("votes");
 
-    if vote_collection.find_one(doc! { "poll_id": &poll_id, "user_id": &user_id }, None).await
+    let this_round = doc! { "$or": [{ "round": poll.round }, { "round": { "$exists": false } }] };
+    let mut existing = doc! { "poll_id": &poll_id, "user_id": &user_id };
+    existing.extend(this_round);
+    if vote_collection.find_one(existing, None).await
         .map_err(|_| WebauthnError::DatabaseError)?.is_some() {
-        return Err(WebauthnError::InvalidInput("User already voted".into()));
+        return Err(WebauthnError::Conflict("User already voted".into()));
     }
 
-    let poll = poll_collection.find_one(doc! { "_id": &poll_id }, None).await
-        .map_err(|_| WebauthnError::DatabaseError)?
-        .ok_or(WebauthnError::UserNotFound)?;
-
-    if poll.is_closed {
+    if !poll.accepting_votes() {
         return Err(WebauthnError::InvalidInput("Poll is closed".into()));
     }
 
     let _ = poll.options.iter().find(|opt| opt.id == vote_req.option_id)
         .ok_or(WebauthnError::InvalidInput("Invalid option ID".into()))?;
 
+    // Counted only if the poll is still on the round it was read in, so a
+    // concurrent reset either archives this vote or never sees it.
+    let count_filter = doc! { "_id": &poll_id, "options.id": &vote_req.option_id, "round": poll.round };
+    let counted = poll_collection.update_one(
+        count_filter.clone(),
+        doc! { "$inc": { "options.$.votes": 1, "total_votes": 1 } },
+        None,
+    ).await.map_err(|_| WebauthnError::DatabaseError)?;
+    if counted.matched_count == 0 {
+        return Err(WebauthnError::Conflict("The poll was reset while voting. Please vote again".into()));
+    }
+
     let vote = Vote {
         id: None,
         poll_id,
         user_id,
         option_id: vote_req.option_id.clone(),
         voted_at: DateTime::now(),
+        round: poll.round,
     };
-    vote_collection.insert_one(vote, None).await
-        .map_err(|_| WebauthnError::DatabaseError)?;
-
-    poll_collection.update_one(
-        doc! { "_id": &poll_id, "options.id": &vote_req.option_id },
-        doc! { "$inc": { "options.$.votes": 1, "total_votes": 1 } },
-        None,
-    ).await.map_err(|_| WebauthnError::DatabaseError)?;
+    if let Err(e) = vote_collection.insert_one(vote, None).await {
+        error!("Failed to store vote: {:?}", e);
+        let _ = poll_collection.update_one(count_filter, doc! { "$inc": { "options.$.votes": -1, "total_votes": -1 } }, None).await;
+        return Err(WebauthnError::DatabaseError);
+    }
 
+    // Anonymous polls never say who voted, not even to webhooks.
+    let voter = (!poll.is_anonymous).then(|| user_id.to_hex());
+    webhooks::dispatch(&app_state, WebhookEvent::VoteCast, &poll_id, &poll, json!({
+        "option_id": vote_req.option_id,
+        "user_id": voter,
+        "total_votes": poll.total_votes + 1,
+    })).await;
     Ok(StatusCode::OK)
 }
 
 pub async fn close_poll(
     Extension(app_state): Extension<AppState>,
-    session: Session,
-    Path(poll_id): Path<String>,
+    ctx: RequestContext,
+    access: PollAccess<CanClose>,
 ) -> Result<impl IntoResponse, WebauthnError> {
-    let user_id = is_authenticated(&session).await?;
-    let poll_id = ObjectId::parse_str(&poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+    record_close(&app_state, &access.poll_id, &access.poll, Some(access.user_id)).await?;
 
-    let poll_collection = app_state.db.collection::<Poll>("polls");
-    let poll = poll_collection.find_one(doc! { "_id": &poll_id }, None).await
-        .map_err(|_| WebauthnError::DatabaseError)?
-        .ok_or(WebauthnError::UserNotFound)?;
+    audit::record(&app_state, &ctx, Some(access.user_id), AuditAction::PollClosed, Some(access.poll_id.to_hex())).await;
+    Ok(StatusCode::OK)
+}
+
+/// Opens a closed poll again, optionally with a new deadline. Without a
+/// body the current deadline is kept if it is still ahead, and dropped if
+/// it has passed; an empty object clears it.
+pub async fn reopen_poll(
+    Extension(app_state): Extension<AppState>,
+    ctx: RequestContext,
+    access: PollAccess<CanReopen>,
+    OptionalJson(deadline): OptionalJson<DeadlineRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let PollAccess { user_id, poll_id, poll, .. } = access;
+    if poll.accepting_votes() {
+        return Err(WebauthnError::Conflict("Poll is already open".into()));
+    }
+    let closes_at = match deadline {
+        Some(deadline) => parse_deadline(deadline.closes_at.as_deref(), deadline.duration_secs)
+            .map_err(|message| WebauthnError::InvalidFields {
+                message: "Deadline is invalid".into(),
+                fields: vec![FieldError::new("closes_at", message)],
+            })?,
+        // Without a body, a deadline still ahead stays; a passed one is dropped.
+        None => poll.closes_at.filter(|closes_at| closes_at.timestamp_millis() > Utc::now().timestamp_millis()),
+    };
 
-    if poll.creator_id != user_id {
-        return Err(WebauthnError::Unauthenticated);
+    // Make sure the history shows the deadline closing it before it reopens.
+    if !poll.is_closed {
+        record_close(&app_state, &poll_id, &poll, None).await?;
     }
 
-    poll_collection.update_one(
+    let transition = to_bson(&PollTransition { state: PollState::Open, at: DateTime::now(), by: Some(user_id) })
+        .map_err(|_| WebauthnError::Unknown)?;
+    app_state.db.collection::<Poll>("polls").update_one(
         doc! { "_id": &poll_id },
-        doc! { "$set": { "is_closed": true } },
+        doc! { "$set": { "is_closed": false, "closes_at": closes_at }, "$push": { "status_history": transition } },
         None,
-    ).await.map_err(|_| WebauthnError::DatabaseError)?;
+    ).await.map_err(|e| { error!("Failed to reopen poll: {:?}", e); WebauthnError::DatabaseError })?;
 
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::PollReopened, Some(poll_id.to_hex())).await;
     Ok(StatusCode::OK)
 }
 
-pub async fn reset_poll(
+/// Moves, sets or clears the deadline of a poll that is still open.
+pub async fn set_deadline(
     Extension(app_state): Extension<AppState>,
-    session: Session,
-    Path(poll_id): Path<String>,
+    ctx: RequestContext,
+    access: PollAccess<CanSetDeadline>,
+    Json(deadline): Json<DeadlineRequest>,
 ) -> Result<impl IntoResponse, WebauthnError> {
-    let user_id = is_authenticated(&session).await?;
-    let poll_id = ObjectId::parse_str(&poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
-
-    let poll_collection = app_state.db.collection::<Poll>("polls");
-    let vote_collection = app_state.db.collection::<Vote>("votes");
+    if !access.poll.accepting_votes() {
+        return Err(WebauthnError::Conflict("Reopen the poll to give it a new deadline".into()));
+    }
+    let closes_at = parse_deadline(deadline.closes_at.as_deref(), deadline.duration_secs)
+        .map_err(|message| WebauthnError::InvalidFields {
+            message: "Deadline is invalid".into(),
+            fields: vec![FieldError::new("closes_at", message)],
+        })?;
+
+    app_state.db.collection::<Poll>("polls").update_one(
+        doc! { "_id": &access.poll_id },
+        doc! { "$set": { "closes_at": closes_at } },
+        None,
+    ).await.map_err(|e| { error!("Failed to update poll deadline: {:?}", e); WebauthnError::DatabaseError })?;
 
-    let poll = poll_collection.find_one(doc! { "_id": &poll_id }, None).await
-        .map_err(|_| WebauthnError::DatabaseError)?
-        .ok_or(WebauthnError::UserNotFound)?;
+    audit::record(&app_state, &ctx, Some(access.user_id), AuditAction::PollDeadlineChanged, Some(access.poll_id.to_hex())).await;
+    Ok(StatusCode::OK)
+}
 
-    if poll.creator_id != user_id {
-        return Err(WebauthnError::Unauthenticated);
+fn parse_deadline(closes_at: Option<&str>, duration_secs: Option<i64>) -> Result<Option<DateTime>, String> {
+    let closes_at = match (closes_at, duration_secs) {
+        (Some(_), Some(_)) => return Err("Give either closes_at or duration_secs, not both".into()),
+        (Some(closes_at), None) => chrono::DateTime::parse_from_rfc3339(closes_at)
+            .map_err(|_| "closes_at must be an RFC 3339 timestamp".to_string())?
+            .timestamp_millis(),
+        (None, Some(secs)) if secs > 0 => Utc::now().timestamp_millis() + secs.saturating_mul(1000),
+        (None, Some(_)) => return Err("duration_secs must be positive".into()),
+        (None, None) => return Ok(None),
+    };
+    if closes_at <= Utc::now().timestamp_millis() {
+        return Err("Deadline must be in the future".into());
     }
+    Ok(Some(DateTime::from_millis(closes_at)))
+}
 
-    poll_collection.update_one(
-        doc! { "_id": &poll_id },
-        doc! { "$set": { "options.$[].votes": 0, "total_votes": 0 } },
+/// Marks an open poll closed and appends the transition to its history.
+/// `by` is `None` when the deadline closed it.
+pub async fn record_close(app_state: &AppState, poll_id: &ObjectId, poll: &Poll, by: Option<ObjectId>) -> Result<(), WebauthnError> {
+    let transition = to_bson(&PollTransition { state: PollState::Closed, at: DateTime::now(), by })
+        .map_err(|_| WebauthnError::Unknown)?;
+    let result = app_state.db.collection::<Poll>("polls").update_one(
+        doc! { "_id": poll_id, "is_closed": false },
+        doc! { "$set": { "is_closed": true }, "$push": { "status_history": transition } },
         None,
-    ).await.map_err(|_| WebauthnError::DatabaseError)?;
+    ).await.map_err(|e| { error!("Failed to close poll: {:?}", e); WebauthnError::DatabaseError })?;
+    if result.modified_count > 0 {
+        dispatch_closed(app_state, poll_id, poll, by).await;
+    }
+    Ok(())
+}
 
-    vote_collection.delete_many(doc! { "poll_id": &poll_id }, None).await
-        .map_err(|_| WebauthnError::DatabaseError)?;
+async fn dispatch_closed(app_state: &AppState, poll_id: &ObjectId, poll: &Poll, by: Option<ObjectId>) {
+    webhooks::dispatch(app_state, WebhookEvent::PollClosed, poll_id, poll, json!({
+        "closed_by": by.map(|id| id.to_hex()),
+        "total_votes": poll.total_votes,
+        "results": option_statistics(&poll.options, poll.total_votes),
+    })).await;
+}
 
+/// Background task that closes polls whose deadline has passed, recording
+/// the deadline itself as the time they closed.
+pub async fn close_expired_polls(app_state: AppState) {
+    let poll_collection = app_state.db.collection::<Poll>("polls");
+    let mut interval = tokio::time::interval(Duration::from_secs(DEADLINE_SWEEP_SECS));
+    loop {
+        interval.tick().await;
+        let expired = match poll_collection.find(doc! { "is_closed": false, "closes_at": { "$lte": DateTime::now() } }, None).await {
+            Ok(cursor) => cursor.try_collect::<Vec<Poll>>().await,
+            Err(e) => Err(e),
+        };
+        let expired = match expired {
+            Ok(expired) => expired,
+            Err(e) => {
+                error!("Failed to find expired polls: {:?}", e);
+                continue;
+            }
+        };
+
+        let close = doc! { "$set": {
+            "is_closed": true,
+            "status_history": { "$concatArrays": [
+                { "$ifNull": ["$status_history", []] },
+                [{ "state": "closed", "at": "$closes_at", "by": null }],
+            ] },
+        } };
+        for poll in expired {
+            let Some(poll_id) = poll.id else { continue };
+            // Someone may have closed or reopened it since the find.
+            match poll_collection.update_one(doc! { "_id": &poll_id, "is_closed": false }, vec![close.clone()], None).await {
+                Ok(result) if result.modified_count > 0 => {
+                    info!("Closed poll {} at its deadline", poll_id);
+                    dispatch_closed(&app_state, &poll_id, &poll, None).await;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to close expired poll {}: {:?}", poll_id, e),
+            }
+        }
+    }
+}
+
+pub async fn reset_poll(
+    Extension(app_state): Extension<AppState>,
+    ctx: RequestContext,
+    access: PollAccess<CanReset>,
+    _verified: RecentlyVerified,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let poll_id = access.poll_id;
+
+    let (poll, round) = rounds::reset_round(&app_state, &poll_id, access.poll.round, access.user_id).await?;
+
+    audit::record(&app_state, &ctx, Some(access.user_id), AuditAction::PollReset, Some(poll_id.to_hex())).await;
+    webhooks::dispatch(&app_state, WebhookEvent::PollReset, &poll_id, &access.poll, json!({
+        "round": round,
+        "archived_votes": poll.total_votes,
+    })).await;
     Ok(StatusCode::OK)
 }
 
 pub async fn poll_results(
     Extension(app_state): Extension<AppState>,
+    session: Session,
     Path(poll_id): Path<String>,
 ) -> Result<Sse<impl futures::Stream<Item = Result<axum::response::sse::Event, WebauthnError>>>, WebauthnError> {
     let poll_id = ObjectId::parse_str(&poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
     let poll_collection = app_state.db.collection::<Poll>("polls");
 
+    let poll = poll_collection.find_one(doc! { "_id": &poll_id }, None).await
+        .map_err(|_| WebauthnError::DatabaseError)?
+        .ok_or(WebauthnError::PollNotFound)?;
+    authorize_view(&app_state, &session, &poll).await?;
+
     let stream = stream! {
         loop {
             let poll = poll_collection.find_one(doc! { "_id": &poll_id }, None).await
                 .map_err(|_| WebauthnError::DatabaseError)?
-                .ok_or(WebauthnError::UserNotFound)?;
+                .ok_or(WebauthnError::PollNotFound)?;
 
             let now = Utc::now();
             let created_at = chrono::DateTime::<Utc>::from_timestamp(poll.created_at.timestamp_millis() / 1000, 0)
@@ -340,12 +744,7 @@ pub async fn poll_results(
 
             let stats = PollStatistics {
                 total_votes: poll.total_votes,
-                options_data: poll.options.iter().map(|opt| OptionStatistics {
-                    id: opt.id.clone(),
-                    text: opt.text.clone(),
-                    votes: opt.votes,
-                    percentage: if poll.total_votes > 0 { (opt.votes as f64 / poll.total_votes as f64) * 100.0 } else { 0.0 },
-                }).collect(),
+                options_data: option_statistics(&poll.options, poll.total_votes),
                 created_at: poll.created_at.to_string(),
                 time_since_creation,
             };
@@ -362,9 +761,95 @@ pub async fn poll_results(
 pub fn routes() -> Router {
     Router::new()
         .route("/api/polls", get(get_polls).post(create_poll))
+        .route("/api/polls/search", get(search_polls))
         .route("/api/polls/:pollId", get(get_poll))
         .route("/api/polls/:pollId/vote", post(vote_poll))
         .route("/api/polls/:pollId/close", post(close_poll))
+        .route("/api/polls/:pollId/reopen", post(reopen_poll))
+        .route("/api/polls/:pollId/deadline", post(set_deadline))
         .route("/api/polls/:pollId/reset", post(reset_poll))
         .route("/api/polls/:pollId/results", get(poll_results))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Relevance ordering itself is Mongo's: `$text` needs a mongod with the
+    // text index, which the tests don't have. What is tested is that the
+    // query asks for it, sorting by `textScore`.
+
+    #[test]
+    fn visitors_see_public_unhidden_polls() {
+        let filter = build_poll_filter(OrgScope::Visible(Vec::new()), None, None, None).unwrap();
+        let empty: Vec<ObjectId> = Vec::new();
+        assert_eq!(filter, doc! {
+            "$or": [{ "org_id": null }, { "org_id": { "$in": empty } }],
+            "is_hidden": { "$ne": true },
+        });
+    }
+
+    #[test]
+    fn members_also_see_their_organizations() {
+        let (user_id, org_id) = (ObjectId::new(), ObjectId::new());
+        let filter = build_poll_filter(OrgScope::Visible(vec![org_id]), Some(user_id), None, None).unwrap();
+        assert_eq!(filter.get_array("$or").unwrap()[1], doc! { "org_id": { "$in": [org_id] } }.into());
+        assert_eq!(filter.get_document("is_hidden").unwrap(), &doc! { "$ne": true });
+    }
+
+    #[test]
+    fn one_organization_replaces_the_visibility_clause() {
+        let (user_id, org_id) = (ObjectId::new(), ObjectId::new());
+        let filter = build_poll_filter(OrgScope::One(org_id), Some(user_id), None, None).unwrap();
+        assert_eq!(filter, doc! { "org_id": org_id, "is_hidden": { "$ne": true } });
+    }
+
+    #[test]
+    fn creators_see_their_own_hidden_polls() {
+        let user_id = ObjectId::new();
+        let filter = build_poll_filter(OrgScope::Visible(Vec::new()), Some(user_id), Some("me".into()), None).unwrap();
+        assert_eq!(filter.get_object_id("creator_id").unwrap(), user_id);
+        assert!(!filter.contains_key("is_hidden"));
+    }
+
+    #[test]
+    fn another_creator_hides_hidden_polls() {
+        let creator_id = ObjectId::new();
+        let filter = build_poll_filter(OrgScope::Visible(Vec::new()), Some(ObjectId::new()), Some(creator_id.to_hex()), None).unwrap();
+        assert_eq!(filter.get_object_id("creator_id").unwrap(), creator_id);
+        assert!(filter.contains_key("is_hidden"));
+    }
+
+    #[test]
+    fn creator_me_needs_a_login() {
+        let result = build_poll_filter(OrgScope::Visible(Vec::new()), None, Some("me".into()), None);
+        assert!(matches!(result, Err(WebauthnError::Unauthenticated)));
+        let result = build_poll_filter(OrgScope::Visible(Vec::new()), None, Some("nonsense".into()), None);
+        assert!(matches!(result, Err(WebauthnError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn closed_filter() {
+        let filter = build_poll_filter(OrgScope::Visible(Vec::new()), None, None, Some(true)).unwrap();
+        assert!(filter.get_bool("is_closed").unwrap());
+    }
+
+    #[test]
+    fn text_search_keeps_the_visibility_rules() {
+        let org_id = ObjectId::new();
+        let filter = build_poll_filter(OrgScope::One(org_id), Some(ObjectId::new()), None, Some(false)).unwrap();
+        let (filter, options) = text_search(filter, "  lunch spot ").unwrap();
+        assert_eq!(filter, doc! {
+            "org_id": org_id,
+            "is_closed": false,
+            "is_hidden": { "$ne": true },
+            "$text": { "$search": "lunch spot" },
+        });
+        assert_eq!(options.sort, Some(doc! { "score": { "$meta": "textScore" } }));
+        assert_eq!(options.limit, Some(SEARCH_RESULT_LIMIT));
+    }
+
+    #[test]
+    fn text_search_needs_a_query() {
+        assert!(matches!(text_search(doc! {}, "   "), Err(WebauthnError::InvalidInput(_))));
+    }
+}
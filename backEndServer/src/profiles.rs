@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Extension,
+    response::IntoResponse,
+    Router, routing::{get, put},
+};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{Collation, CollationStrength, FindOneOptions, FindOptions};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::{current_user, User};
+use crate::error::WebauthnError;
+use crate::extract::{parse_id, Json, Path};
+use crate::polls::{find_poll_responses, PollResponse};
+use crate::reauth::RecentlyVerified;
+use crate::startup::AppState;
+use crate::usernames;
+
+const DISPLAY_NAME_MAX_CHARS: usize = 64;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    /// Empty or absent clears the display name, so the username is shown.
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeUsernameRequest {
+    pub username: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileResponse {
+    pub id: String,
+    pub username: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicProfileResponse {
+    pub id: String,
+    pub username: String,
+    pub display_name: String,
+    /// Polls anyone can see: no organization, not hidden. Newest first.
+    pub polls: Vec<PollResponse>,
+}
+
+impl From<&User> for ProfileResponse {
+    fn from(user: &User) -> Self {
+        ProfileResponse {
+            id: user.id.map(|id| id.to_string()).unwrap_or_default(),
+            username: user.username.clone(),
+            display_name: user.display_name().to_string(),
+        }
+    }
+}
+
+/// Usernames are unique regardless of case: the unique index on
+/// `users.username` is built with this collation, and lookups pass it too
+/// so that they match the same way and can use the index.
+pub fn username_collation() -> Collation {
+    Collation::builder().locale("en".to_string()).strength(CollationStrength::Secondary).build()
+}
+
+/// Accepts the name as typed: it is matched in canonical form, and as is
+/// for accounts that predate `usernames`.
+pub async fn find_by_username(app_state: &AppState, username: &str) -> Result<Option<User>, WebauthnError> {
+    let options = FindOneOptions::builder().collation(username_collation()).build();
+    let candidates = vec![usernames::canonicalize(username), username.to_string()];
+    app_state.db.collection::<User>("users").find_one(doc! { "username": { "$in": candidates } }, options).await
+        .map_err(|e| { error!("Database error during user search: {:?}", e); WebauthnError::DatabaseError })
+}
+
+/// Whether a write failed on a unique index.
+pub fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(error.kind.as_ref(), ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == 11000)
+}
+
+/// Trims a display name and checks it. An empty name becomes `None`.
+pub fn normalize_display_name(display_name: &str) -> Result<Option<String>, WebauthnError> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() {
+        return Ok(None);
+    }
+    if display_name.chars().count() > DISPLAY_NAME_MAX_CHARS {
+        return Err(WebauthnError::InvalidInput(format!("Display name cannot be longer than {} characters", DISPLAY_NAME_MAX_CHARS)));
+    }
+    if display_name.chars().any(char::is_control) {
+        return Err(WebauthnError::InvalidInput("Display name cannot contain control characters".into()));
+    }
+    Ok(Some(display_name.to_string()))
+}
+
+pub async fn get_profile(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (_, user) = current_user(&app_state, &session).await?;
+    Ok(Json(ProfileResponse::from(&user)))
+}
+
+pub async fn update_profile(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Json(req): Json<UpdateProfileRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, mut user) = current_user(&app_state, &session).await?;
+    user.display_name = match req.display_name {
+        Some(display_name) => normalize_display_name(&display_name)?,
+        None => None,
+    };
+    let update = match &user.display_name {
+        Some(display_name) => doc! { "$set": { "display_name": display_name } },
+        None => doc! { "$unset": { "display_name": "" } },
+    };
+    app_state.db.collection::<User>("users").update_one(doc! { "_id": &user_id }, update, None).await
+        .map_err(|e| { error!("Failed to update profile: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(Json(ProfileResponse::from(&user)))
+}
+
+/// The canonical form of `requested`, unless it is `current` already.
+/// Fails when it breaks the policy or looks like someone else's username,
+/// which includes differing from it only in case.
+async fn new_username(app_state: &AppState, user_id: &ObjectId, current: &str, requested: &str) -> Result<Option<String>, WebauthnError> {
+    let username = usernames::validate(requested)?;
+    if username == current {
+        return Ok(None);
+    }
+    usernames::ensure_not_confusable(app_state, &username, Some(user_id)).await?;
+    Ok(Some(username))
+}
+
+/// Renames the caller. The username is what they log in with, so this
+/// needs a recent passkey re-authentication. Passkeys are tied to the
+/// account's UUID, not its name, and keep working.
+pub async fn change_username(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    _verified: RecentlyVerified,
+    Json(req): Json<ChangeUsernameRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, mut user) = current_user(&app_state, &session).await?;
+    let Some(username) = new_username(&app_state, &user_id, &user.username, &req.username).await? else {
+        return Ok(Json(ProfileResponse::from(&user)));
+    };
+
+    // The unique index has the final say; a taken name fails the write.
+    let update = doc! { "$set": { "username": &username, "username_skeleton": usernames::skeleton(&username) } };
+    app_state.db.collection::<User>("users").update_one(doc! { "_id": &user_id }, update, None).await
+        .map_err(|e| {
+            if is_duplicate_key(&e) {
+                info!("Username '{}' is already taken", username);
+                return WebauthnError::UserExists;
+            }
+            error!("Failed to change username: {:?}", e);
+            WebauthnError::DatabaseError
+        })?;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::UsernameChanged, Some(user_id.to_hex())).await;
+    info!("User {} renamed from '{}' to '{}'", user_id, user.username, username);
+    user.username = username;
+    Ok(Json(ProfileResponse::from(&user)))
+}
+
+/// Anyone's public profile. Suspended accounts are not shown.
+pub async fn get_public_profile(
+    Extension(app_state): Extension<AppState>,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = parse_id(&params, "userId", "user")?;
+    let user = app_state.db.collection::<User>("users").find_one(doc! { "_id": &user_id }, None).await
+        .map_err(|e| { error!("Database error during user lookup: {:?}", e); WebauthnError::DatabaseError })?
+        .filter(|user| !user.suspended)
+        .ok_or(WebauthnError::UserNotFound)?;
+
+    let filter: Document = doc! { "creator_id": &user_id, "org_id": null, "is_hidden": { "$ne": true } };
+    let options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+    let polls = find_poll_responses(&app_state, filter, Some(options)).await?;
+
+    Ok(Json(PublicProfileResponse {
+        id: user_id.to_string(),
+        username: user.username.clone(),
+        display_name: user.display_name().to_string(),
+        polls,
+    }))
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/account/profile", get(get_profile).patch(update_profile))
+        .route("/api/account/username", put(change_username))
+        .route("/api/users/:userId", get(get_public_profile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::startup::test_state;
+
+    #[test]
+    fn display_names_are_trimmed_and_empty_ones_cleared() {
+        assert_eq!(normalize_display_name("  Alice Liddell ").unwrap().as_deref(), Some("Alice Liddell"));
+        assert_eq!(normalize_display_name("   ").unwrap(), None);
+        assert_eq!(normalize_display_name("").unwrap(), None);
+    }
+
+    #[test]
+    fn display_names_are_limited_in_characters() {
+        assert!(normalize_display_name(&"a".repeat(DISPLAY_NAME_MAX_CHARS)).is_ok());
+        assert!(normalize_display_name(&"a".repeat(DISPLAY_NAME_MAX_CHARS + 1)).is_err());
+        // Within the limit in characters, well past it in bytes.
+        assert!(normalize_display_name(&"ж".repeat(DISPLAY_NAME_MAX_CHARS)).is_ok());
+    }
+
+    #[test]
+    fn display_names_cannot_hold_control_characters() {
+        assert!(normalize_display_name("Alice\nBob").is_err());
+        assert!(normalize_display_name("Alice\u{7}").is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_TEST_URI"]
+    async fn renames_to_look_alikes_of_other_usernames_are_refused() {
+        let app_state = test_state("profiles").await;
+        let (alice, pop, bob) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        for (user_id, username) in [(alice, "alice"), (pop, "pop"), (bob, "bob")] {
+            app_state.db.collection::<Document>("users").insert_one(doc! {
+                "_id": user_id, "username": username, "username_skeleton": usernames::skeleton(username),
+            }, None).await.unwrap();
+        }
+
+        // Differs only in case.
+        assert!(new_username(&app_state, &bob, "bob", "ALICE").await.is_err());
+        // All Cyrillic, and looks just like "pop".
+        assert!(new_username(&app_state, &bob, "bob", "рор").await.is_err());
+        // Renaming to a look-alike of one's own name is fine.
+        assert_eq!(new_username(&app_state, &pop, "pop", "рор").await.unwrap().as_deref(), Some("рор"));
+        assert_eq!(new_username(&app_state, &bob, "bob", "Bob").await.unwrap(), None);
+        assert_eq!(new_username(&app_state, &bob, "bob", "robert").await.unwrap().as_deref(), Some("robert"));
+
+        app_state.db.drop(None).await.unwrap();
+    }
+}
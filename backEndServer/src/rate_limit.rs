@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::Method,
+    response::{IntoResponse, Response},
+};
+use futures::future::BoxFuture;
+use mongodb::bson::oid::ObjectId;
+use percent_encoding::percent_decode_str;
+use tower::{Layer, Service};
+use tower_sessions::Session;
+
+use crate::api_tokens;
+use crate::config::Config;
+use crate::error::WebauthnError;
+use crate::usernames;
+
+/// Buckets are only swept once the store grows past this many keys.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// A token bucket: up to `capacity` requests at once, refilled evenly so
+/// that `capacity` more are allowed every `period_secs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period_secs: u64,
+}
+
+impl RateLimit {
+    fn refill_per_sec(&self) -> f64 {
+        self.capacity as f64 / self.period_secs as f64
+    }
+}
+
+/// Parses `"<capacity>/<period_secs>"`, e.g. `"10/60"` for ten a minute.
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (capacity, period) = value.split_once('/').ok_or("expected <capacity>/<period_secs>")?;
+        let capacity: u32 = capacity.trim().parse().map_err(|_| "capacity must be a number")?;
+        let period_secs: u64 = period.trim().parse().map_err(|_| "period must be a number of seconds")?;
+        if capacity == 0 || period_secs == 0 {
+            return Err("capacity and period must be positive".into());
+        }
+        Ok(RateLimit { capacity, period_secs })
+    }
+}
+
+/// Where bucket state lives. Everything here is in-process; a shared store
+/// would be needed once the server runs as more than one instance.
+pub trait RateLimitStore: Send + Sync {
+    /// Takes a token from the bucket under `key`, or says how long until
+    /// one is available.
+    fn acquire(&self, key: &str, limit: RateLimit, now: Instant) -> Result<(), Duration>;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// When the bucket will have refilled, after which it can be forgotten.
+    full_at: Instant,
+}
+
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    fn acquire(&self, key: &str, limit: RateLimit, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if buckets.len() > PRUNE_THRESHOLD {
+            // A bucket that has had time to refill completely is the same as no bucket.
+            buckets.retain(|_, bucket| bucket.full_at > now);
+        }
+
+        let capacity = limit.capacity as f64;
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket { tokens: capacity, updated_at: now, full_at: now });
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.refill_per_sec()).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.full_at = now + Duration::from_secs_f64((capacity - bucket.tokens) / limit.refill_per_sec());
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limit.refill_per_sec()))
+        }
+    }
+}
+
+/// Which account a limited request is charged to.
+#[derive(Debug, Clone, Copy)]
+enum AccountKey {
+    /// The `:username` path parameter, for the login, registration and
+    /// recovery ceremonies where nobody is logged in yet.
+    Username,
+    /// The logged-in user, by session or API token.
+    CurrentUser,
+}
+
+struct Rule {
+    name: &'static str,
+    method: Method,
+    path: &'static str,
+    per_ip: RateLimit,
+    per_account: RateLimit,
+    account: AccountKey,
+}
+
+/// Applies per-IP and per-account token buckets to the endpoints that are
+/// cheap to call and expensive to serve: starting a ceremony stores
+/// challenge state in the session store, and votes and new polls write to
+/// the database.
+pub struct RateLimiter {
+    rules: Vec<Rule>,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config, store: Arc<dyn RateLimitStore>) -> Self {
+        let rules = vec![
+            Rule { name: "register", method: Method::POST, path: "/register_start/:username", per_ip: config.rate_limit_auth_ip, per_account: config.rate_limit_auth_account, account: AccountKey::Username },
+            Rule { name: "login", method: Method::POST, path: "/login_start/:username", per_ip: config.rate_limit_auth_ip, per_account: config.rate_limit_auth_account, account: AccountKey::Username },
+            Rule { name: "recover", method: Method::POST, path: "/recover/:username", per_ip: config.rate_limit_auth_ip, per_account: config.rate_limit_auth_account, account: AccountKey::Username },
+            Rule { name: "vote", method: Method::POST, path: "/api/polls/:pollId/vote", per_ip: config.rate_limit_vote_ip, per_account: config.rate_limit_vote_account, account: AccountKey::CurrentUser },
+            Rule { name: "create_poll", method: Method::POST, path: "/api/polls", per_ip: config.rate_limit_create_poll_ip, per_account: config.rate_limit_create_poll_account, account: AccountKey::CurrentUser },
+        ];
+        RateLimiter { rules, store }
+    }
+
+    /// Picks the rule a request falls under and who it should be charged to.
+    /// Done up front because the request body can't be held across an await.
+    fn classify(&self, req: &Request) -> Option<Charge> {
+        let matched = req.extensions().get::<MatchedPath>()?;
+        let rule = self.rules.iter().position(|rule| rule.method == req.method() && rule.path == matched.as_str())?;
+        let ip = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_string());
+        let account = match self.rules[rule].account {
+            AccountKey::Username => Account::Known(username_key(req.uri().path())),
+            AccountKey::CurrentUser => match api_tokens::current_user_id() {
+                Some(user_id) => Account::Known(user_id.to_hex()),
+                None => Account::Session(req.extensions().get::<Session>().cloned()),
+            },
+        };
+        Some(Charge { rule, ip, account })
+    }
+
+    /// Takes a token from every bucket the request falls under. On refusal,
+    /// returns the number of seconds to wait.
+    async fn charge(&self, charge: Charge) -> Result<(), u64> {
+        let rule = &self.rules[charge.rule];
+        let now = Instant::now();
+        if let Some(ip) = charge.ip {
+            self.store.acquire(&format!("{}:ip:{}", rule.name, ip), rule.per_ip, now).map_err(retry_after_secs)?;
+        }
+        let account = match charge.account {
+            Account::Known(account) => Some(account),
+            Account::Session(Some(session)) => session.get::<ObjectId>("user_id").await.ok().flatten().map(|user_id| user_id.to_hex()),
+            Account::Session(None) => None,
+        };
+        if let Some(account) = account.filter(|account| !account.is_empty()) {
+            self.store.acquire(&format!("{}:account:{}", rule.name, account), rule.per_account, now).map_err(|wait| {
+                info!("Rate limited {} for account {}", rule.name, account);
+                retry_after_secs(wait)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+enum Account {
+    Known(String),
+    /// Resolved to a user ID only when charging, since that needs an await.
+    Session(Option<Session>),
+}
+
+struct Charge {
+    rule: usize,
+    ip: Option<String>,
+    account: Account,
+}
+
+/// The username is the last path segment. It is charged in the canonical
+/// form the handlers look it up by, so that spelling the same name with
+/// other case, percent-encoding or full-width letters buys no extra attempts.
+fn username_key(path: &str) -> String {
+    let segment = path.rsplit('/').next().unwrap_or_default();
+    usernames::canonicalize(&percent_decode_str(segment).decode_utf8_lossy())
+}
+
+fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// Tower layer wrapping every route with `RateLimiter`. Add it to the router
+/// inside the session and bearer token layers so it can see who is calling.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: RateLimiter) -> Self {
+        RateLimitLayer { limiter: Arc::new(limiter) }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService { inner, limiter: self.limiter.clone() }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // Use the service that was polled ready and leave a fresh clone behind.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        let charge = limiter.classify(&req);
+        Box::pin(async move {
+            if let Some(charge) = charge {
+                if let Err(retry_after) = limiter.charge(charge).await {
+                    return Ok(WebauthnError::RateLimited { retry_after }.into_response());
+                }
+            }
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::{header, StatusCode}, routing::post, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit { capacity: 2, period_secs: 60 };
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let store = MemoryRateLimitStore::default();
+        let start = Instant::now();
+        assert!(store.acquire("k", LIMIT, start).is_ok());
+        assert!(store.acquire("k", LIMIT, start).is_ok());
+        let wait = store.acquire("k", LIMIT, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(30));
+
+        // One token comes back every 30s, and no more than `capacity` pile up.
+        assert!(store.acquire("k", LIMIT, start + Duration::from_secs(29)).is_err());
+        assert!(store.acquire("k", LIMIT, start + Duration::from_secs(30)).is_ok());
+        let later = start + Duration::from_secs(3600);
+        assert!(store.acquire("k", LIMIT, later).is_ok());
+        assert!(store.acquire("k", LIMIT, later).is_ok());
+        assert!(store.acquire("k", LIMIT, later).is_err());
+    }
+
+    #[test]
+    fn buckets_are_per_key() {
+        let store = MemoryRateLimitStore::default();
+        let now = Instant::now();
+        assert!(store.acquire("a", LIMIT, now).is_ok());
+        assert!(store.acquire("a", LIMIT, now).is_ok());
+        assert!(store.acquire("a", LIMIT, now).is_err());
+        assert!(store.acquire("b", LIMIT, now).is_ok());
+    }
+
+    #[test]
+    fn username_key_is_canonical() {
+        assert_eq!(username_key("/login_start/alice"), "alice");
+        assert_eq!(username_key("/login_start/Alice"), "alice");
+        assert_eq!(username_key("/login_start/%41lice"), "alice");
+        assert_eq!(username_key("/login_start/%EF%BC%A1lice"), "alice");
+    }
+
+    /// A router with a stub login endpoint behind the limiter: 3 requests
+    /// per IP and 2 per account a minute.
+    fn app() -> Router {
+        let mut config = Config::from_env();
+        config.rate_limit_auth_ip = RateLimit { capacity: 3, period_secs: 60 };
+        config.rate_limit_auth_account = LIMIT;
+        Router::new()
+            .route("/login_start/:username", post(|| async { StatusCode::OK }))
+            .layer(RateLimitLayer::new(RateLimiter::new(&config, Arc::new(MemoryRateLimitStore::default()))))
+    }
+
+    async fn login(app: &Router, username: &str, ip: [u8; 4]) -> Response {
+        let mut req = Request::post(format!("/login_start/{}", username)).body(Body::empty()).unwrap();
+        req.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 1234))));
+        app.clone().oneshot(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn ip_and_account_buckets_are_separate() {
+        let app = app();
+        assert_eq!(login(&app, "alice", [10, 0, 0, 1]).await.status(), StatusCode::OK);
+        assert_eq!(login(&app, "alice", [10, 0, 0, 1]).await.status(), StatusCode::OK);
+        // The account bucket follows alice to another address.
+        assert_eq!(login(&app, "alice", [10, 0, 0, 2]).await.status(), StatusCode::TOO_MANY_REQUESTS);
+        // bob gets a separate account bucket, but shares the first address's IP bucket.
+        assert_eq!(login(&app, "bob", [10, 0, 0, 1]).await.status(), StatusCode::OK);
+        assert_eq!(login(&app, "bob", [10, 0, 0, 1]).await.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(login(&app, "bob", [10, 0, 0, 3]).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn spellings_of_a_username_share_a_bucket() {
+        let app = app();
+        assert_eq!(login(&app, "Alice", [10, 0, 0, 1]).await.status(), StatusCode::OK);
+        assert_eq!(login(&app, "%41lice", [10, 0, 0, 2]).await.status(), StatusCode::OK);
+        assert_eq!(login(&app, "%EF%BC%A1lice", [10, 0, 0, 3]).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn refusal_is_a_429_with_retry_after() {
+        let app = app();
+        login(&app, "alice", [10, 0, 0, 1]).await;
+        login(&app, "alice", [10, 0, 0, 1]).await;
+        let response = login(&app, "alice", [10, 0, 0, 1]).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/problem+json");
+    }
+}
@@ -0,0 +1,139 @@
+use axum::{
+    async_trait,
+    extract::{Extension, FromRequestParts},
+    http::{request::Parts, StatusCode},
+    response::IntoResponse,
+    Router, routing::post,
+};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use tower_sessions::Session;
+use webauthn_rs::prelude::*;
+
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::{current_user, User};
+use crate::ceremonies::{self, CeremonyQuery};
+use crate::clone_detection;
+use crate::error::WebauthnError;
+use crate::extract::{Json, Query};
+use crate::startup::AppState;
+
+/// When the session's user last proved presence with a user-verifying
+/// passkey assertion, as milliseconds since the epoch.
+const VERIFIED_AT_KEY: &str = "verified_at";
+const REAUTH_STATE_KEY: &str = "reauth_state";
+
+/// Records that the session's user has just completed a user-verifying
+/// ceremony. Logging in counts.
+pub async fn mark_verified(session: &Session) -> Result<(), WebauthnError> {
+    session.insert(VERIFIED_AT_KEY, DateTime::now().timestamp_millis()).await
+        .map_err(|e| { error!("Failed to store verification time: {:?}", e); WebauthnError::CorruptSession })
+}
+
+/// Fails with `ReauthenticationRequired` unless the session was verified
+/// within the configured window. Bearer tokens never pass, as there is no
+/// session for them to verify.
+pub async fn require_recent(app_state: &AppState, session: &Session) -> Result<(), WebauthnError> {
+    let verified_at = session.get::<i64>(VERIFIED_AT_KEY).await
+        .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+    let window_ms = app_state.config.reauth_window_secs as i64 * 1000;
+    match verified_at {
+        Some(at) if DateTime::now().timestamp_millis() - at <= window_ms => Ok(()),
+        _ => Err(WebauthnError::ReauthenticationRequired),
+    }
+}
+
+/// Extractor guarding destructive handlers with `require_recent`. Put it
+/// after the extractors that authenticate and authorize the caller, so
+/// that those errors take precedence.
+pub struct RecentlyVerified;
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RecentlyVerified {
+    type Rejection = WebauthnError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(app_state) = Extension::<AppState>::from_request_parts(parts, state).await
+            .map_err(|e| { error!("AppState missing from request: {:?}", e); WebauthnError::Unknown })?;
+        let session = Session::from_request_parts(parts, state).await
+            .map_err(|e| { error!("Session missing from request: {:?}", e); WebauthnError::CorruptSession })?;
+        require_recent(&app_state, &session).await?;
+        Ok(RecentlyVerified)
+    }
+}
+
+/// A passkey marked as possibly cloned can't unlock destructive actions
+/// unless the policy is only to warn; the user has to recover first.
+fn refuse_marked(app_state: &AppState, user: &User) -> Result<(), WebauthnError> {
+    if clone_detection::check_marked(app_state.config.counter_regression_policy, user)? {
+        Ok(())
+    } else {
+        Err(WebauthnError::PasskeyPossiblyCloned)
+    }
+}
+
+/// Starts a re-authentication ceremony for the logged-in user.
+pub async fn start_reauth(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, user) = current_user(&app_state, &session).await?;
+    let _ = session.remove_value(REAUTH_STATE_KEY).await;
+    refuse_marked(&app_state, &user)?;
+
+    let (rcr, auth_state) = app_state.webauthn.start_passkey_authentication(std::slice::from_ref(&user.keys))
+        .map_err(|e| { error!("WebAuthn re-authentication initialization error: {:?}", e); WebauthnError::Unknown })?;
+    ceremonies::begin(&app_state, &session, REAUTH_STATE_KEY, (user_id, auth_state), rcr).await
+}
+
+/// Completes re-authentication. The assertion must be user-verified; on
+/// success the session ID is cycled, since the session now carries more
+/// privilege than before.
+pub async fn finish_reauth(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Query(query): Query<CeremonyQuery>,
+    Json(auth): Json<PublicKeyCredential>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, user) = current_user(&app_state, &session).await?;
+    let (state_user_id, auth_state): (ObjectId, PasskeyAuthentication) = ceremonies::take(&session, REAUTH_STATE_KEY, query.ceremony).await?;
+    if state_user_id != user_id {
+        error!("Re-authentication state belongs to user {}, not {}", state_user_id, user_id);
+        return Err(WebauthnError::CorruptSession);
+    }
+    refuse_marked(&app_state, &user)?;
+
+    let auth_result = match app_state.webauthn.finish_passkey_authentication(&auth, &auth_state) {
+        Ok(auth_result) if auth_result.user_verified() => auth_result,
+        // Whatever the login policy, a possibly cloned passkey is no proof.
+        Err(webauthn_rs::prelude::WebauthnError::CredentialPossibleCompromise) => {
+            clone_detection::flag_possible_clone(&app_state, &ctx, &user_id).await;
+            return Err(WebauthnError::PasskeyPossiblyCloned);
+        }
+        result => {
+            info!("Re-authentication failed for user {}: {:?}", user_id, result.err());
+            audit::record(&app_state, &ctx, Some(user_id), AuditAction::ReauthenticationFailed, Some(user_id.to_hex())).await;
+            return Err(WebauthnError::InvalidCredential);
+        }
+    };
+
+    // Only the passkey is written back; the rest of `user` may be stale.
+    let mut keys = user.keys;
+    if keys.update_credential(&auth_result).is_some() {
+        let keys = mongodb::bson::to_bson(&keys).map_err(|_| WebauthnError::Unknown)?;
+        app_state.db.collection::<User>("users").update_one(doc! { "_id": &user_id }, doc! { "$set": { "keys": keys } }, None).await
+            .map_err(|e| { error!("Failed to update user credential: {:?}", e); WebauthnError::DatabaseError })?;
+    }
+
+    session.cycle_id().await
+        .map_err(|e| { error!("Failed to cycle session ID: {:?}", e); WebauthnError::CorruptSession })?;
+    mark_verified(&session).await?;
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::Reauthenticated, Some(user_id.to_hex())).await;
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/auth/reauth_start", post(start_reauth))
+        .route("/api/auth/reauth_finish", post(finish_reauth))
+}
@@ -0,0 +1,206 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{get, post},
+};
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tower_sessions::Session;
+use uuid::Uuid;
+use webauthn_rs::prelude::*;
+
+use crate::attestation::RegistrationCeremony;
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::{current_user, User};
+use crate::ceremonies::{self, CeremonyQuery};
+use crate::error::WebauthnError;
+use crate::extract::{Json, Path, Query};
+use crate::profiles;
+use crate::reauth::RecentlyVerified;
+use crate::sessions;
+use crate::startup::AppState;
+
+pub const CODE_COUNT: usize = 10;
+const CODE_GROUPS: usize = 3;
+const CODE_GROUP_LEN: usize = 4;
+/// No 0/O or 1/I/L, so codes survive being written down.
+const CODE_ALPHABET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+/// How long a recovery session can be used to register a new passkey.
+const RECOVERY_SESSION_MS: i64 = 10 * 60 * 1000;
+/// Session key holding `(user_id, expires_at_ms)` for a recovery session.
+/// Deliberately not `user_id`: a recovery session is not a login.
+const RECOVERY_USER_KEY: &str = "recovery_user";
+const RECOVERY_REG_STATE_KEY: &str = "recovery_reg_state";
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryStatusResponse {
+    pub remaining: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecoverRequest {
+    pub code: String,
+}
+
+/// Codes are hashed with the user's UUID, so equal codes on different
+/// accounts hash differently. Case, spaces and dashes don't matter.
+pub fn hash_code(user_uuid: &Uuid, code: &str) -> String {
+    let normalized: String = code.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_uppercase()).collect();
+    let mut hasher = Sha256::new();
+    hasher.update(user_uuid.as_bytes());
+    hasher.update(normalized.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// A fresh set of codes and the hashes to store for them.
+pub fn generate_codes(user_uuid: &Uuid) -> (Vec<String>, Vec<String>) {
+    let mut rng = rand::thread_rng();
+    let codes: Vec<String> = (0..CODE_COUNT)
+        .map(|_| (0..CODE_GROUPS)
+            .map(|_| (0..CODE_GROUP_LEN).map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("-"))
+        .collect();
+    let hashes = codes.iter().map(|code| hash_code(user_uuid, code)).collect();
+    (codes, hashes)
+}
+
+/// How many unused codes the caller has left.
+pub async fn recovery_status(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (_, user) = current_user(&app_state, &session).await?;
+    Ok(Json(RecoveryStatusResponse { remaining: user.recovery_code_hashes.len() }))
+}
+
+/// Replaces the caller's recovery codes with a new set. The old codes stop
+/// working immediately.
+pub async fn regenerate_codes(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    _verified: RecentlyVerified,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let (user_id, user) = current_user(&app_state, &session).await?;
+    let (codes, hashes) = generate_codes(&user.uuid);
+    app_state.db.collection::<User>("users")
+        .update_one(doc! { "_id": &user_id }, doc! { "$set": { "recovery_code_hashes": hashes } }, None).await
+        .map_err(|e| { error!("Failed to store recovery codes: {:?}", e); WebauthnError::DatabaseError })?;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::RecoveryCodesGenerated, Some(user_id.to_hex())).await;
+    Ok(Json(RecoveryCodesResponse { recovery_codes: codes }))
+}
+
+/// Trades a recovery code for a short-lived session that can do nothing but
+/// register a new passkey. The code is used up either way once it matches.
+pub async fn recover(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(username): Path<String>,
+    Json(req): Json<RecoverRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user = profiles::find_by_username(&app_state, &username).await?
+        .ok_or_else(|| { info!("Recovery attempted for unknown user '{}'", username); WebauthnError::InvalidCredential })?;
+    let user_id = user.id.ok_or(WebauthnError::DatabaseError)?;
+    if user.suspended {
+        info!("Suspended user '{}' tried to recover their account", username);
+        return Err(WebauthnError::AccountSuspended);
+    }
+
+    // Pulling the hash in the same write that matches it keeps a code from
+    // being used twice by concurrent requests.
+    let hash = hash_code(&user.uuid, &req.code);
+    let result = app_state.db.collection::<User>("users").update_one(
+        doc! { "_id": &user_id, "recovery_code_hashes": &hash },
+        doc! { "$pull": { "recovery_code_hashes": &hash } },
+        None,
+    ).await.map_err(|e| { error!("Failed to use recovery code: {:?}", e); WebauthnError::DatabaseError })?;
+    if result.modified_count == 0 {
+        info!("Invalid recovery code for user '{}'", username);
+        audit::record(&app_state, &ctx, None, AuditAction::LoginFailed, Some(user_id.to_hex())).await;
+        return Err(WebauthnError::InvalidCredential);
+    }
+
+    session.cycle_id().await
+        .map_err(|e| { error!("Failed to cycle session ID: {:?}", e); WebauthnError::CorruptSession })?;
+    let expires_at = DateTime::now().timestamp_millis() + RECOVERY_SESSION_MS;
+    session.insert(RECOVERY_USER_KEY, (user_id, expires_at)).await
+        .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::RecoveryCodeUsed, Some(user_id.to_hex())).await;
+    info!("User '{}' started account recovery", username);
+    Ok(StatusCode::OK)
+}
+
+async fn recovery_user(app_state: &AppState, session: &Session) -> Result<User, WebauthnError> {
+    let (user_id, expires_at) = session.get::<(ObjectId, i64)>(RECOVERY_USER_KEY).await?
+        .ok_or_else(|| { info!("No recovery session"); WebauthnError::Unauthenticated })?;
+    if DateTime::now().timestamp_millis() > expires_at {
+        info!("Recovery session of user {} expired", user_id);
+        let _ = session.remove_value(RECOVERY_USER_KEY).await;
+        return Err(WebauthnError::Unauthenticated);
+    }
+    app_state.db.collection::<User>("users").find_one(doc! { "_id": &user_id }, None).await
+        .map_err(|e| { error!("Database error during user lookup: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::UserNotFound)
+}
+
+pub async fn start_recovery_register(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user = recovery_user(&app_state, &session).await?;
+    let _ = session.remove_value(RECOVERY_REG_STATE_KEY).await;
+
+    let (ccr, reg_state) = app_state.authenticator_policy.start_registration(&app_state.webauthn, user.uuid, &user.username, user.display_name())
+        .map_err(|e| { error!("WebAuthn registration initialization error: {:?}", e); WebauthnError::Unknown })?;
+    ceremonies::begin(&app_state, &session, RECOVERY_REG_STATE_KEY, reg_state, ccr).await
+}
+
+/// Replaces the account's passkey with the one just registered. The lost
+/// passkey may be in someone else's hands, so every existing session is
+/// ended and the user is logged in afresh.
+pub async fn finish_recovery_register(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Query(query): Query<CeremonyQuery>,
+    Json(reg): Json<RegisterPublicKeyCredential>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user = recovery_user(&app_state, &session).await?;
+    let user_id = user.id.ok_or(WebauthnError::DatabaseError)?;
+    let reg_state: RegistrationCeremony = ceremonies::take(&session, RECOVERY_REG_STATE_KEY, query.ceremony).await?;
+
+    let passkey = reg_state.finish(&app_state.webauthn, &reg)
+        .map_err(|e| { error!("WebAuthn registration completion error: {:?}", e); WebauthnError::InvalidCredential })?;
+    let keys = mongodb::bson::to_bson(&passkey).map_err(|_| WebauthnError::Unknown)?;
+    app_state.db.collection::<User>("users")
+        .update_one(doc! { "_id": &user_id }, doc! { "$set": { "keys": keys }, "$unset": { "passkey_possibly_cloned_at": "" } }, None).await
+        .map_err(|e| { error!("Failed to store recovered passkey: {:?}", e); WebauthnError::DatabaseError })?;
+
+    sessions::revoke_all(&app_state, &user_id).await?;
+    let _ = session.remove_value(RECOVERY_USER_KEY).await;
+    sessions::establish(&app_state, &session, &ctx, user_id).await?;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::PasskeyRecovered, Some(user_id.to_hex())).await;
+    info!("User {} registered a new passkey through recovery", user_id);
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/recover/:username", post(recover))
+        .route("/recover_register_start", post(start_recovery_register))
+        .route("/recover_register_finish", post(finish_recovery_register))
+        .route("/api/account/recovery_codes", get(recovery_status).post(regenerate_codes))
+}
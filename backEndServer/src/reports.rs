@@ -0,0 +1,401 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{get, post},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tower_sessions::Session;
+
+use crate::admin::require_admin;
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::User;
+use crate::error::WebauthnError;
+use crate::extract::{parse_id, Json, Path, Query};
+use crate::permissions::{CanAppeal, CanView, PollAccess};
+use crate::polls::Poll;
+use crate::startup::AppState;
+
+const MAX_TEXT_LEN: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Offensive,
+    Harassment,
+    Misinformation,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportStatus {
+    Open,
+    Resolved,
+    Dismissed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Report {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub poll_id: ObjectId,
+    pub reporter_id: ObjectId,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    pub created_at: DateTime,
+    pub resolved_by: Option<ObjectId>,
+    pub resolved_at: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppealStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Appeal {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub poll_id: ObjectId,
+    pub creator_id: ObjectId,
+    pub message: String,
+    pub status: AppealStatus,
+    pub created_at: DateTime,
+    pub decided_by: Option<ObjectId>,
+    pub decided_at: Option<DateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportRequest {
+    pub reason: ReportReason,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AppealRequest {
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportQueryParams {
+    status: Option<ReportStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AppealQueryParams {
+    status: Option<AppealStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReportResponse {
+    pub id: String,
+    pub poll_id: String,
+    pub poll_title: String,
+    pub poll_hidden: bool,
+    pub reporter_username: String,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppealResponse {
+    pub id: String,
+    pub poll_id: String,
+    pub poll_title: String,
+    pub creator_username: String,
+    pub message: String,
+    pub status: AppealStatus,
+    pub created_at: String,
+}
+
+async fn set_poll_hidden(app_state: &AppState, poll_id: &ObjectId, hidden: bool) -> Result<(), WebauthnError> {
+    app_state.db.collection::<Poll>("polls").update_one(
+        doc! { "_id": poll_id },
+        doc! { "$set": { "is_hidden": hidden } },
+        None,
+    ).await.map_err(|e| { error!("Failed to update poll visibility: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(())
+}
+
+async fn open_report_count(app_state: &AppState, poll_id: &ObjectId) -> Result<u64, WebauthnError> {
+    app_state.db.collection::<Report>("reports")
+        .count_documents(doc! { "poll_id": poll_id, "status": "open" }, None).await
+        .map_err(|e| { error!("Failed to count reports: {:?}", e); WebauthnError::DatabaseError })
+}
+
+async fn find_poll(app_state: &AppState, poll_id: &ObjectId) -> Result<Option<Poll>, WebauthnError> {
+    app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": poll_id }, None).await
+        .map_err(|e| { error!("Failed to fetch poll: {:?}", e); WebauthnError::DatabaseError })
+}
+
+async fn username_of(app_state: &AppState, user_id: &ObjectId) -> Result<String, WebauthnError> {
+    Ok(app_state.db.collection::<User>("users").find_one(doc! { "_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch user: {:?}", e); WebauthnError::DatabaseError })?
+        .map(|user| user.username)
+        .unwrap_or_default())
+}
+
+/// Files a report against a poll. Once the poll has collected
+/// `report_hide_threshold` open reports it is hidden until a moderator
+/// looks at it.
+pub async fn report_poll(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanView>,
+    Json(req): Json<ReportRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let details = req.details.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    if details.as_ref().is_some_and(|d| d.chars().count() > MAX_TEXT_LEN) {
+        return Err(WebauthnError::InvalidInput(format!("Report details cannot be longer than {} characters", MAX_TEXT_LEN)));
+    }
+    if access.poll.creator_id == access.user_id {
+        return Err(WebauthnError::InvalidInput("You cannot report your own poll".into()));
+    }
+
+    let report_collection = app_state.db.collection::<Report>("reports");
+    if report_collection.find_one(doc! { "poll_id": &access.poll_id, "reporter_id": &access.user_id, "status": "open" }, None).await
+        .map_err(|e| { error!("Failed to fetch report: {:?}", e); WebauthnError::DatabaseError })?.is_some() {
+        return Err(WebauthnError::Conflict("You already reported this poll".into()));
+    }
+
+    let report = Report {
+        id: None,
+        poll_id: access.poll_id,
+        reporter_id: access.user_id,
+        reason: req.reason,
+        details,
+        status: ReportStatus::Open,
+        created_at: DateTime::now(),
+        resolved_by: None,
+        resolved_at: None,
+    };
+    report_collection.insert_one(report, None).await
+        .map_err(|e| { error!("Failed to insert report: {:?}", e); WebauthnError::DatabaseError })?;
+
+    if !access.poll.is_hidden && open_report_count(&app_state, &access.poll_id).await? >= app_state.config.report_hide_threshold {
+        set_poll_hidden(&app_state, &access.poll_id, true).await?;
+        info!("Poll {} hidden after reaching the report threshold", access.poll_id);
+    }
+
+    Ok(StatusCode::CREATED)
+}
+
+/// Lets the creator of a hidden poll ask a moderator to restore it.
+pub async fn appeal_poll(
+    Extension(app_state): Extension<AppState>,
+    access: PollAccess<CanAppeal>,
+    Json(req): Json<AppealRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let message = req.message.trim();
+    if message.is_empty() || message.chars().count() > MAX_TEXT_LEN {
+        return Err(WebauthnError::InvalidInput(format!("Appeal message must be between 1 and {} characters", MAX_TEXT_LEN)));
+    }
+    if !access.poll.is_hidden {
+        return Err(WebauthnError::InvalidInput("Only hidden polls can be appealed".into()));
+    }
+
+    let appeal_collection = app_state.db.collection::<Appeal>("appeals");
+    if appeal_collection.find_one(doc! { "poll_id": &access.poll_id, "status": "pending" }, None).await
+        .map_err(|e| { error!("Failed to fetch appeal: {:?}", e); WebauthnError::DatabaseError })?.is_some() {
+        return Err(WebauthnError::Conflict("An appeal for this poll is already pending".into()));
+    }
+
+    let appeal = Appeal {
+        id: None,
+        poll_id: access.poll_id,
+        creator_id: access.user_id,
+        message: message.to_string(),
+        status: AppealStatus::Pending,
+        created_at: DateTime::now(),
+        decided_by: None,
+        decided_at: None,
+    };
+    appeal_collection.insert_one(appeal, None).await
+        .map_err(|e| { error!("Failed to insert appeal: {:?}", e); WebauthnError::DatabaseError })?;
+
+    Ok(StatusCode::CREATED)
+}
+
+pub async fn list_reports(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<ReportQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    require_admin(&app_state, &session).await?;
+    let status = to_bson(&params.status.unwrap_or(ReportStatus::Open)).map_err(|_| WebauthnError::Unknown)?;
+
+    let mut cursor = app_state.db.collection::<Report>("reports")
+        .find(doc! { "status": status }, None).await
+        .map_err(|e| { error!("Failed to fetch reports: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut reports = Vec::new();
+    while let Some(report) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect reports: {:?}", e); WebauthnError::DatabaseError })? {
+        let poll = find_poll(&app_state, &report.poll_id).await?;
+        reports.push(ReportResponse {
+            id: report.id.map(|id| id.to_string()).unwrap_or_default(),
+            poll_id: report.poll_id.to_string(),
+            poll_title: poll.as_ref().map(|p| p.title.clone()).unwrap_or_default(),
+            poll_hidden: poll.as_ref().is_some_and(|p| p.is_hidden),
+            reporter_username: username_of(&app_state, &report.reporter_id).await?,
+            reason: report.reason,
+            details: report.details,
+            status: report.status,
+            created_at: report.created_at.to_string(),
+        });
+    }
+    Ok(Json(reports))
+}
+
+async fn close_report(app_state: &AppState, report_id: &ObjectId, admin_id: ObjectId, status: ReportStatus) -> Result<Report, WebauthnError> {
+    let status = to_bson(&status).map_err(|_| WebauthnError::Unknown)?;
+    app_state.db.collection::<Report>("reports").find_one_and_update(
+        doc! { "_id": report_id, "status": "open" },
+        doc! { "$set": { "status": status, "resolved_by": admin_id, "resolved_at": DateTime::now() } },
+        None,
+    ).await
+        .map_err(|e| { error!("Failed to update report: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or_else(|| WebauthnError::InvalidInput("Open report not found".into()))
+}
+
+/// Upholds a report: the poll stays hidden.
+pub async fn resolve_report(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let report_id = parse_id(&params, "reportId", "report")?;
+
+    let report = close_report(&app_state, &report_id, admin_id, ReportStatus::Resolved).await?;
+    set_poll_hidden(&app_state, &report.poll_id, true).await?;
+
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::ReportResolved, Some(report_id.to_hex())).await;
+    info!("Report {} upheld by admin {}", report_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+/// Rejects a report. If that leaves the poll under the hide threshold and
+/// no report against it has been upheld, the poll becomes visible again.
+pub async fn dismiss_report(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let report_id = parse_id(&params, "reportId", "report")?;
+
+    let report = close_report(&app_state, &report_id, admin_id, ReportStatus::Dismissed).await?;
+    let upheld = app_state.db.collection::<Report>("reports")
+        .count_documents(doc! { "poll_id": &report.poll_id, "status": "resolved" }, None).await
+        .map_err(|e| { error!("Failed to count reports: {:?}", e); WebauthnError::DatabaseError })?;
+    if upheld == 0 && open_report_count(&app_state, &report.poll_id).await? < app_state.config.report_hide_threshold {
+        set_poll_hidden(&app_state, &report.poll_id, false).await?;
+    }
+
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::ReportDismissed, Some(report_id.to_hex())).await;
+    info!("Report {} dismissed by admin {}", report_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn list_appeals(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(params): Query<AppealQueryParams>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    require_admin(&app_state, &session).await?;
+    let status = to_bson(&params.status.unwrap_or(AppealStatus::Pending)).map_err(|_| WebauthnError::Unknown)?;
+
+    let mut cursor = app_state.db.collection::<Appeal>("appeals")
+        .find(doc! { "status": status }, None).await
+        .map_err(|e| { error!("Failed to fetch appeals: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut appeals = Vec::new();
+    while let Some(appeal) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect appeals: {:?}", e); WebauthnError::DatabaseError })? {
+        let poll = find_poll(&app_state, &appeal.poll_id).await?;
+        appeals.push(AppealResponse {
+            id: appeal.id.map(|id| id.to_string()).unwrap_or_default(),
+            poll_id: appeal.poll_id.to_string(),
+            poll_title: poll.map(|p| p.title).unwrap_or_default(),
+            creator_username: username_of(&app_state, &appeal.creator_id).await?,
+            message: appeal.message,
+            status: appeal.status,
+            created_at: appeal.created_at.to_string(),
+        });
+    }
+    Ok(Json(appeals))
+}
+
+async fn decide_appeal(app_state: &AppState, appeal_id: &ObjectId, admin_id: ObjectId, status: AppealStatus) -> Result<Appeal, WebauthnError> {
+    let status = to_bson(&status).map_err(|_| WebauthnError::Unknown)?;
+    app_state.db.collection::<Appeal>("appeals").find_one_and_update(
+        doc! { "_id": appeal_id, "status": "pending" },
+        doc! { "$set": { "status": status, "decided_by": admin_id, "decided_at": DateTime::now() } },
+        None,
+    ).await
+        .map_err(|e| { error!("Failed to update appeal: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or_else(|| WebauthnError::InvalidInput("Pending appeal not found".into()))
+}
+
+/// Restores the poll and dismisses every open report against it.
+pub async fn accept_appeal(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let appeal_id = parse_id(&params, "appealId", "appeal")?;
+
+    let appeal = decide_appeal(&app_state, &appeal_id, admin_id, AppealStatus::Accepted).await?;
+    app_state.db.collection::<Report>("reports").update_many(
+        doc! { "poll_id": &appeal.poll_id, "status": { "$in": ["open", "resolved"] } },
+        doc! { "$set": { "status": "dismissed", "resolved_by": admin_id, "resolved_at": DateTime::now() } },
+        None,
+    ).await.map_err(|e| { error!("Failed to dismiss reports: {:?}", e); WebauthnError::DatabaseError })?;
+    set_poll_hidden(&app_state, &appeal.poll_id, false).await?;
+
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::AppealAccepted, Some(appeal_id.to_hex())).await;
+    info!("Appeal {} accepted by admin {}", appeal_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn reject_appeal(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let admin_id = require_admin(&app_state, &session).await?;
+    let appeal_id = parse_id(&params, "appealId", "appeal")?;
+
+    decide_appeal(&app_state, &appeal_id, admin_id, AppealStatus::Rejected).await?;
+    audit::record(&app_state, &ctx, Some(admin_id), AuditAction::AppealRejected, Some(appeal_id.to_hex())).await;
+    info!("Appeal {} rejected by admin {}", appeal_id, admin_id);
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/polls/:pollId/report", post(report_poll))
+        .route("/api/polls/:pollId/appeal", post(appeal_poll))
+        .route("/api/admin/reports", get(list_reports))
+        .route("/api/admin/reports/:reportId/resolve", post(resolve_report))
+        .route("/api/admin/reports/:reportId/dismiss", post(dismiss_report))
+        .route("/api/admin/appeals", get(list_appeals))
+        .route("/api/admin/appeals/:appealId/accept", post(accept_appeal))
+        .route("/api/admin/appeals/:appealId/reject", post(reject_appeal))
+}
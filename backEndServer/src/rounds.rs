@@ -0,0 +1,291 @@
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{get, post},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime};
+use mongodb::options::{FindOneAndUpdateOptions, FindOneOptions, FindOptions, ReturnDocument};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::error::WebauthnError;
+use crate::extract::{Json, Path};
+use crate::permissions::{authorize_view, CanReset, PollAccess};
+use crate::polls::{option_statistics, OptionStatistics, Poll, PollOption, Vote};
+use crate::startup::AppState;
+
+/// The tally and ballots of a poll as they stood just before a reset.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PollRound {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub poll_id: ObjectId,
+    /// 1-based; round N is what was on the poll before its Nth reset.
+    pub round: i32,
+    pub options: Vec<PollOption>,
+    pub total_votes: i32,
+    pub archived_at: DateTime,
+    pub reset_by: ObjectId,
+    pub undo_until: DateTime,
+    pub undone: bool,
+}
+
+/// A ballot moved out of `votes` by a reset. Keeps the original vote ID so
+/// an undo restores it exactly.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedVote {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub poll_id: ObjectId,
+    pub round: i32,
+    pub user_id: ObjectId,
+    pub option_id: String,
+    pub voted_at: DateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoundResponse {
+    pub round: i32,
+    pub total_votes: i32,
+    pub options_data: Vec<OptionStatistics>,
+    pub archived_at: String,
+    pub undo_until: String,
+    pub undone: bool,
+}
+
+/// Zeroes the tally of a poll on round `round` and archives that round,
+/// returning the poll as it was and the number of the archived round.
+pub async fn reset_round(app_state: &AppState, poll_id: &ObjectId, round: i32, reset_by: ObjectId) -> Result<(Poll, i32), WebauthnError> {
+    // Moving the poll to the next round is the commit point: votes counted
+    // before it are in the tally returned here, later ones are refused or
+    // go to the new round. See `vote_poll`.
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::Before).build();
+    let poll = app_state.db.collection::<Poll>("polls").find_one_and_update(
+        doc! { "_id": poll_id, "round": round },
+        doc! { "$set": { "options.$[].votes": 0, "total_votes": 0 }, "$inc": { "round": 1 } },
+        options,
+    ).await.map_err(|e| { error!("Failed to reset poll: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or_else(|| WebauthnError::Conflict("The poll was reset at the same time".into()))?;
+
+    let archived = archive_round(app_state, &poll, poll_id, reset_by).await?;
+    Ok((poll, archived))
+}
+
+/// Moves the ballots of the round `poll` was on out of `votes` and
+/// snapshots its tally, once `reset_round` has zeroed the poll and moved it
+/// on, with the poll as it was just before.
+async fn archive_round(app_state: &AppState, poll: &Poll, poll_id: &ObjectId, reset_by: ObjectId) -> Result<i32, WebauthnError> {
+    let round = poll.round + 1;
+    let now = DateTime::now();
+    let undo_window_ms = app_state.config.reset_undo_window_secs as i64 * 1000;
+
+    // Anything not already cast in the new round; matches votes that
+    // predate `Vote::round` too.
+    let vote_collection = app_state.db.collection::<Vote>("votes");
+    let mut cursor = vote_collection.find(doc! { "poll_id": poll_id, "round": { "$ne": round } }, None).await
+        .map_err(|e| { error!("Failed to fetch votes: {:?}", e); WebauthnError::DatabaseError })?;
+    let mut ballots = Vec::new();
+    while let Some(vote) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect votes: {:?}", e); WebauthnError::DatabaseError })? {
+        ballots.push(ArchivedVote {
+            id: vote.id.ok_or(WebauthnError::DatabaseError)?,
+            poll_id: *poll_id,
+            round,
+            user_id: vote.user_id,
+            option_id: vote.option_id,
+            voted_at: vote.voted_at,
+        });
+    }
+    if !ballots.is_empty() {
+        let ids: Vec<ObjectId> = ballots.iter().map(|ballot| ballot.id).collect();
+        app_state.db.collection::<ArchivedVote>("archived_votes").insert_many(ballots, None).await
+            .map_err(|e| { error!("Failed to archive votes: {:?}", e); WebauthnError::DatabaseError })?;
+        // By ID, so a ballot stored since the find stays in place.
+        vote_collection.delete_many(doc! { "_id": { "$in": ids } }, None).await
+            .map_err(|e| { error!("Failed to delete archived votes: {:?}", e); WebauthnError::DatabaseError })?;
+    }
+
+    let snapshot = PollRound {
+        id: None,
+        poll_id: *poll_id,
+        round,
+        options: poll.options.clone(),
+        total_votes: poll.total_votes,
+        archived_at: now,
+        reset_by,
+        undo_until: DateTime::from_millis(now.timestamp_millis() + undo_window_ms),
+        undone: false,
+    };
+    app_state.db.collection::<PollRound>("poll_rounds").insert_one(snapshot, None).await
+        .map_err(|e| { error!("Failed to store poll round: {:?}", e); WebauthnError::DatabaseError })?;
+
+    Ok(round)
+}
+
+pub async fn list_rounds(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(poll_id): Path<String>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let poll_id = ObjectId::parse_str(&poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+    let poll = app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": &poll_id }, None).await
+        .map_err(|_| WebauthnError::DatabaseError)?
+        .ok_or(WebauthnError::PollNotFound)?;
+    authorize_view(&app_state, &session, &poll).await?;
+
+    let options = FindOptions::builder().sort(doc! { "round": -1 }).build();
+    let mut cursor = app_state.db.collection::<PollRound>("poll_rounds")
+        .find(doc! { "poll_id": &poll_id, "undone": false }, options).await
+        .map_err(|e| { error!("Failed to fetch poll rounds: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut rounds = Vec::new();
+    while let Some(round) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect poll rounds: {:?}", e); WebauthnError::DatabaseError })? {
+        rounds.push(RoundResponse {
+            round: round.round,
+            total_votes: round.total_votes,
+            options_data: option_statistics(&round.options, round.total_votes),
+            archived_at: round.archived_at.to_string(),
+            undo_until: round.undo_until.to_string(),
+            undone: round.undone,
+        });
+    }
+    Ok(Json(rounds))
+}
+
+/// Puts back the tally and ballots of the most recent reset, as long as the
+/// undo window is still open and nobody has voted since.
+pub async fn undo_reset(
+    Extension(app_state): Extension<AppState>,
+    ctx: RequestContext,
+    access: PollAccess<CanReset>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let PollAccess { user_id, poll_id, poll, .. } = access;
+    let round = undo_round(&app_state, &poll_id, poll.round).await?;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::PollResetUndone, Some(poll_id.to_hex())).await;
+    info!("Reset of poll {} round {} undone", poll_id, round);
+    Ok(StatusCode::OK)
+}
+
+/// Puts back the tally and ballots of the reset that left the poll on
+/// round `current`, returning the number of the restored round's snapshot.
+async fn undo_round(app_state: &AppState, poll_id: &ObjectId, current: i32) -> Result<i32, WebauthnError> {
+    let round_collection = app_state.db.collection::<PollRound>("poll_rounds");
+
+    // Only the latest live snapshot: an undone reset leaves its snapshot
+    // behind, and the next reset reuses its number.
+    let options = FindOneOptions::builder().sort(doc! { "archived_at": -1 }).build();
+    let round = round_collection.find_one(doc! { "poll_id": poll_id, "round": current, "undone": false }, options).await
+        .map_err(|e| { error!("Failed to fetch poll round: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or_else(|| WebauthnError::Conflict("There is no reset to undo".into()))?;
+    if round.undo_until < DateTime::now() {
+        return Err(WebauthnError::Conflict("The undo window for this reset has passed".into()));
+    }
+
+    // Restore the tally only if the poll is still on this round with no
+    // votes. Like a reset, this moves the round, so a concurrent undo, reset
+    // or vote can't slip in between: see `vote_poll`.
+    let options = to_bson(&round.options).map_err(|_| WebauthnError::Unknown)?;
+    let restored = app_state.db.collection::<Poll>("polls").update_one(
+        doc! { "_id": poll_id, "round": round.round, "total_votes": 0 },
+        doc! { "$set": { "options": options, "total_votes": round.total_votes, "round": round.round - 1 } },
+        None,
+    ).await.map_err(|e| { error!("Failed to restore poll tally: {:?}", e); WebauthnError::DatabaseError })?;
+    if restored.modified_count == 0 {
+        return Err(WebauthnError::Conflict("Votes have been cast since the reset".into()));
+    }
+    round_collection.update_one(
+        doc! { "_id": &round.id },
+        doc! { "$set": { "undone": true } },
+        None,
+    ).await.map_err(|e| { error!("Failed to update poll round: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let archive_collection = app_state.db.collection::<ArchivedVote>("archived_votes");
+    let archive_filter = doc! { "poll_id": poll_id, "round": round.round };
+    let mut cursor = archive_collection.find(archive_filter.clone(), None).await
+        .map_err(|e| { error!("Failed to fetch archived votes: {:?}", e); WebauthnError::DatabaseError })?;
+    let mut votes = Vec::new();
+    while let Some(ballot) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect archived votes: {:?}", e); WebauthnError::DatabaseError })? {
+        votes.push(Vote {
+            id: Some(ballot.id),
+            poll_id: ballot.poll_id,
+            user_id: ballot.user_id,
+            option_id: ballot.option_id,
+            voted_at: ballot.voted_at,
+            round: round.round - 1,
+        });
+    }
+    if !votes.is_empty() {
+        app_state.db.collection::<Vote>("votes").insert_many(votes, None).await
+            .map_err(|e| { error!("Failed to restore votes: {:?}", e); WebauthnError::DatabaseError })?;
+    }
+
+    archive_collection.delete_many(archive_filter, None).await
+        .map_err(|e| { error!("Failed to delete archived votes: {:?}", e); WebauthnError::DatabaseError })?;
+
+    Ok(round.round)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/polls/:pollId/rounds", get(list_rounds))
+        .route("/api/polls/:pollId/reset/undo", post(undo_reset))
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::Document;
+
+    use super::*;
+    use crate::startup::test_state;
+
+    async fn poll(app_state: &AppState, poll_id: &ObjectId) -> Poll {
+        app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": poll_id }, None).await.unwrap().unwrap()
+    }
+
+    async fn live_rounds(app_state: &AppState, poll_id: &ObjectId) -> u64 {
+        app_state.db.collection::<PollRound>("poll_rounds")
+            .count_documents(doc! { "poll_id": poll_id, "undone": false }, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MongoDB server at MONGODB_TEST_URI"]
+    async fn a_second_undo_restores_the_second_reset() {
+        let app_state = test_state("rounds").await;
+        let poll_id = ObjectId::new();
+        let user_id = ObjectId::new();
+        app_state.db.collection::<Document>("polls").insert_one(doc! {
+            "_id": poll_id,
+            "title": "Lunch",
+            "options": [{ "id": "a", "text": "Pizza", "votes": 1 }],
+            "creator_id": user_id,
+            "created_at": DateTime::now(),
+            "is_closed": false,
+            "total_votes": 1,
+        }, None).await.unwrap();
+        app_state.db.collection::<Document>("votes").insert_one(doc! {
+            "poll_id": poll_id, "user_id": user_id, "option_id": "a", "voted_at": DateTime::now(), "round": 0,
+        }, None).await.unwrap();
+
+        for _ in 0..2 {
+            let (_, archived) = reset_round(&app_state, &poll_id, 0, user_id).await.unwrap();
+            assert_eq!(archived, 1);
+            assert_eq!(poll(&app_state, &poll_id).await.total_votes, 0);
+            assert_eq!(live_rounds(&app_state, &poll_id).await, 1);
+
+            assert_eq!(undo_round(&app_state, &poll_id, 1).await.unwrap(), 1);
+            let restored = poll(&app_state, &poll_id).await;
+            assert_eq!((restored.round, restored.total_votes), (0, 1));
+            assert_eq!(live_rounds(&app_state, &poll_id).await, 0);
+            let ballots = app_state.db.collection::<Vote>("votes").count_documents(doc! { "poll_id": &poll_id }, None).await.unwrap();
+            assert_eq!(ballots, 1);
+        }
+
+        app_state.db.drop(None).await.unwrap();
+    }
+}
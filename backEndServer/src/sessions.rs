@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{delete, get},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, DateTime};
+use mongodb::options::FindOptions;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::audit::{self, AuditAction, RequestContext};
+use crate::auth::is_authenticated;
+use crate::error::WebauthnError;
+use crate::extract::{parse_id, Json, Path};
+use crate::startup::AppState;
+
+/// Key of the `user_sessions` entry in the cookie session.
+const SESSION_REF_KEY: &str = "session_ref";
+/// `last_seen_at` is only written when it is at least this stale.
+const LAST_SEEN_RESOLUTION_MS: i64 = 60 * 1000;
+
+/// The server-side record of a logged-in session, so a user can see where
+/// they are logged in and end those sessions. The cookie session only
+/// counts while its entry here is live.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UserSession {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: ObjectId,
+    pub created_at: DateTime,
+    pub last_seen_at: DateTime,
+    /// Hard end of the session, however active it is.
+    pub expires_at: DateTime,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserSessionResponse {
+    pub id: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub expires_at: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub current: bool,
+}
+
+/// Empties the cookie session and gives it a new ID. Nothing from before
+/// a login may carry over into it: an ID planted beforehand would let
+/// someone else ride the session, and a leftover `verified_at` or
+/// recovery user would vouch for whoever logs in next.
+async fn start_afresh(session: &Session) -> Result<(), WebauthnError> {
+    session.clear().await;
+    session.cycle_id().await
+        .map_err(|e| { error!("Failed to cycle session ID: {:?}", e); WebauthnError::CorruptSession })
+}
+
+/// Starts a fresh session for `user_id`; see `start_afresh`.
+pub async fn establish(app_state: &AppState, session: &Session, ctx: &RequestContext, user_id: ObjectId) -> Result<(), WebauthnError> {
+    start_afresh(session).await?;
+
+    let now = DateTime::now();
+    let lifetime_ms = app_state.config.session_max_lifetime_secs as i64 * 1000;
+    let entry = UserSession {
+        id: None,
+        user_id,
+        created_at: now,
+        last_seen_at: now,
+        expires_at: DateTime::from_millis(now.timestamp_millis() + lifetime_ms),
+        ip: ctx.ip.clone(),
+        user_agent: ctx.user_agent.clone(),
+        revoked: false,
+    };
+    let result = app_state.db.collection::<UserSession>("user_sessions").insert_one(entry, None).await
+        .map_err(|e| { error!("Failed to record session: {:?}", e); WebauthnError::DatabaseError })?;
+    let session_ref = result.inserted_id.as_object_id().ok_or(WebauthnError::DatabaseError)?;
+
+    session.insert("user_id", user_id).await
+        .map_err(|e| { error!("Failed to store user ID in session: {:?}", e); WebauthnError::CorruptSession })?;
+    session.insert(SESSION_REF_KEY, session_ref).await
+        .map_err(|e| { error!("Failed to store session reference: {:?}", e); WebauthnError::CorruptSession })?;
+    Ok(())
+}
+
+/// Checks that the cookie session for `user_id` is still live: not revoked
+/// and not past its absolute lifetime. Dead sessions are flushed.
+pub async fn validate(app_state: &AppState, session: &Session, user_id: &ObjectId) -> Result<(), WebauthnError> {
+    let session_ref = session.get::<ObjectId>(SESSION_REF_KEY).await
+        .map_err(|e| { error!("Session error: {:?}", e); WebauthnError::CorruptSession })?;
+    let now = DateTime::now();
+    let collection = app_state.db.collection::<UserSession>("user_sessions");
+    let entry = match session_ref {
+        Some(session_ref) => collection.find_one(
+            doc! { "_id": &session_ref, "user_id": user_id, "revoked": false, "expires_at": { "$gt": now } },
+            None,
+        ).await.map_err(|e| { error!("Failed to fetch session: {:?}", e); WebauthnError::DatabaseError })?,
+        None => None,
+    };
+
+    let Some(entry) = entry else {
+        info!("Session of user {} is revoked or expired", user_id);
+        let _ = session.flush().await;
+        return Err(WebauthnError::Unauthenticated);
+    };
+    if now.timestamp_millis() - entry.last_seen_at.timestamp_millis() >= LAST_SEEN_RESOLUTION_MS {
+        if let Err(e) = collection.update_one(doc! { "_id": &entry.id }, doc! { "$set": { "last_seen_at": now } }, None).await {
+            warn!("Failed to update session last seen time: {:?}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Ends the current session, both its index entry and the cookie session.
+pub async fn end_current(app_state: &AppState, session: &Session) -> Result<(), WebauthnError> {
+    if let Ok(Some(session_ref)) = session.get::<ObjectId>(SESSION_REF_KEY).await {
+        app_state.db.collection::<UserSession>("user_sessions")
+            .update_one(doc! { "_id": &session_ref }, doc! { "$set": { "revoked": true } }, None).await
+            .map_err(|e| { error!("Failed to revoke session: {:?}", e); WebauthnError::DatabaseError })?;
+    }
+    session.flush().await
+        .map_err(|e| { error!("Failed to flush session: {:?}", e); WebauthnError::CorruptSession })
+}
+
+/// Ends every session of a user, e.g. after their privileges change.
+pub async fn revoke_all(app_state: &AppState, user_id: &ObjectId) -> Result<u64, WebauthnError> {
+    let result = app_state.db.collection::<UserSession>("user_sessions")
+        .update_many(doc! { "user_id": user_id, "revoked": false }, doc! { "$set": { "revoked": true } }, None).await
+        .map_err(|e| { error!("Failed to revoke sessions: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(result.modified_count)
+}
+
+/// The caller's live sessions, most recently used first.
+pub async fn list_sessions(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let current = session.get::<ObjectId>(SESSION_REF_KEY).await.ok().flatten();
+
+    let options = FindOptions::builder().sort(doc! { "last_seen_at": -1 }).build();
+    let mut cursor = app_state.db.collection::<UserSession>("user_sessions")
+        .find(doc! { "user_id": &user_id, "revoked": false, "expires_at": { "$gt": DateTime::now() } }, options).await
+        .map_err(|e| { error!("Failed to fetch sessions: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut sessions = Vec::new();
+    while let Some(entry) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect sessions: {:?}", e); WebauthnError::DatabaseError })? {
+        sessions.push(UserSessionResponse {
+            id: entry.id.map(|id| id.to_string()).unwrap_or_default(),
+            created_at: entry.created_at.to_string(),
+            last_seen_at: entry.last_seen_at.to_string(),
+            expires_at: entry.expires_at.to_string(),
+            ip: entry.ip,
+            user_agent: entry.user_agent,
+            current: entry.id.is_some() && entry.id == current,
+        });
+    }
+    Ok(Json(sessions))
+}
+
+pub async fn revoke_session(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let session_id = parse_id(&params, "sessionId", "session")?;
+
+    let result = app_state.db.collection::<UserSession>("user_sessions")
+        .update_one(doc! { "_id": &session_id, "user_id": &user_id, "revoked": false }, doc! { "$set": { "revoked": true } }, None).await
+        .map_err(|e| { error!("Failed to revoke session: {:?}", e); WebauthnError::DatabaseError })?;
+    if result.matched_count == 0 {
+        return Err(WebauthnError::SessionNotFound);
+    }
+    if session.get::<ObjectId>(SESSION_REF_KEY).await.ok().flatten() == Some(session_id) {
+        let _ = session.flush().await;
+    }
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::SessionRevoked, Some(user_id.to_hex())).await;
+    Ok(StatusCode::OK)
+}
+
+/// Logs the caller out everywhere, this session included.
+pub async fn revoke_all_sessions(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    ctx: RequestContext,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let revoked = revoke_all(&app_state, &user_id).await?;
+    let _ = session.flush().await;
+
+    audit::record(&app_state, &ctx, Some(user_id), AuditAction::AllSessionsRevoked, Some(user_id.to_hex())).await;
+    info!("User {} revoked {} sessions", user_id, revoked);
+    Ok(StatusCode::OK)
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/auth/sessions", get(list_sessions).delete(revoke_all_sessions))
+        .route("/api/auth/sessions/:sessionId", delete(revoke_session))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tower_sessions::MemoryStore;
+
+    use super::*;
+    use crate::reauth;
+
+    #[tokio::test]
+    async fn a_new_login_keeps_nothing_from_the_old_session() {
+        let session = Session::new(None, Arc::new(MemoryStore::default()), None);
+        session.insert("user_id", ObjectId::new()).await.unwrap();
+        reauth::mark_verified(&session).await.unwrap();
+        session.insert("recovery_user", ObjectId::new()).await.unwrap();
+        session.insert("auth_state", "pending").await.unwrap();
+        session.save().await.unwrap();
+        let old_id = session.id();
+
+        start_afresh(&session).await.unwrap();
+        session.save().await.unwrap();
+
+        assert_ne!(session.id(), old_id);
+        for key in ["user_id", "verified_at", "recovery_user", "auth_state"] {
+            assert!(session.get_value(key).await.unwrap().is_none(), "{} survived", key);
+        }
+    }
+}
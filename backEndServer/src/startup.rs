@@ -1,11 +1,29 @@
 use std::sync::Arc;
+use tokio::sync::Mutex;
 use webauthn_rs::prelude::*;
-use mongodb::{Client, Database};
+use mongodb::{bson::doc, options::IndexOptions, Client, Database, IndexModel};
+
+use crate::api_tokens::ApiToken;
+use crate::attestation::AuthenticatorPolicy;
+use crate::audit::{AuditEvent, ChainHead};
+use crate::auth::User;
+use crate::config::Config;
+use crate::orgs::OrgMembership;
+use crate::polls::Poll;
+use crate::profiles::username_collation;
+use crate::sessions::UserSession;
+use crate::usernames;
+use crate::webhooks::Delivery;
 
 #[derive(Clone)]
 pub struct AppState {
     pub webauthn: Arc<Webauthn>,
+    pub authenticator_policy: Arc<AuthenticatorPolicy>,
     pub db: Database,
+    pub config: Arc<Config>,
+    /// Serialises appends to the audit chain, and holds the last record this
+    /// process appended so that verification notices if it disappears.
+    pub audit_head: Arc<Mutex<Option<ChainHead>>>,
 }
 
 impl AppState {
@@ -14,13 +32,110 @@ impl AppState {
             .await
             .expect("Failed to connect to MongoDB");
         let db = client.database("auth_db");
+        ensure_indexes(&db).await;
+        usernames::backfill_skeletons(&db).await;
 
+        let config = Arc::new(Config::from_env());
         let rp_id = "localhost";
         let rp_origin = Url::parse("http://localhost:8081").expect("Invalid URL"); // Matches frontend
         let builder = WebauthnBuilder::new(rp_id, &rp_origin).expect("Invalid configuration");
         let builder = builder.rp_name("Axum Webauthn-rs");
+        // Browsers give up on the prompt when the ceremony does; see `ceremonies`.
+        let builder = builder.timeout(std::time::Duration::from_secs(config.ceremony_ttl_secs));
         let webauthn = Arc::new(builder.build().expect("Invalid configuration"));
         println!("Connected to MongoDB");
-        AppState { webauthn, db }
+        let authenticator_policy = Arc::new(AuthenticatorPolicy::from_config(&config));
+        if config.audit_hmac_key.is_none() {
+            warn!("AUDIT_HMAC_KEY is not set; anyone with database access can rewrite the audit log undetected");
+        }
+        AppState { webauthn, authenticator_policy, db, config, audit_head: Arc::new(Mutex::new(None)) }
     }
-}
\ No newline at end of file
+}
+
+/// State backed by a throwaway database on the server at
+/// `MONGODB_TEST_URI`, for tests that need MongoDB. Those are `#[ignore]`d
+/// so that the suite runs without one.
+#[cfg(test)]
+pub async fn test_state(name: &str) -> AppState {
+    let uri = std::env::var("MONGODB_TEST_URI").expect("MONGODB_TEST_URI must be set");
+    let client = Client::with_uri_str(uri).await.expect("Failed to connect to MongoDB");
+    let db = client.database(&format!("{}_test_{}", name, mongodb::bson::oid::ObjectId::new()));
+    ensure_indexes(&db).await;
+    let config = Arc::new(Config::from_env());
+    let origin = Url::parse("http://localhost:8081").expect("Invalid URL");
+    let webauthn = Arc::new(WebauthnBuilder::new("localhost", &origin).expect("Invalid configuration").build().expect("Invalid configuration"));
+    let authenticator_policy = Arc::new(AuthenticatorPolicy::from_config(&config));
+    AppState { webauthn, authenticator_policy, db, config, audit_head: Arc::new(Mutex::new(None)) }
+}
+
+async fn ensure_indexes(db: &Database) {
+    // Backs /api/polls/search; titles weigh more than option texts when ranking.
+    let poll_text_index = IndexModel::builder()
+        .keys(doc! { "title": "text", "options.text": "text" })
+        .options(IndexOptions::builder()
+            .name("poll_text_search".to_string())
+            .weights(doc! { "title": 3, "options.text": 1 })
+            .build())
+        .build();
+    db.collection::<Poll>("polls").create_index(poll_text_index, None).await
+        .expect("Failed to create poll text index");
+
+    let membership_index = IndexModel::builder()
+        .keys(doc! { "org_id": 1, "user_id": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    db.collection::<OrgMembership>("org_members").create_index(membership_index, None).await
+        .expect("Failed to create org membership index");
+
+    let audit_seq_index = IndexModel::builder()
+        .keys(doc! { "seq": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    db.collection::<AuditEvent>("audit_events").create_index(audit_seq_index, None).await
+        .expect("Failed to create audit sequence index");
+
+    let token_hash_index = IndexModel::builder()
+        .keys(doc! { "token_hash": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    db.collection::<ApiToken>("api_tokens").create_index(token_hash_index, None).await
+        .expect("Failed to create API token index");
+
+    // The webhook worker claims due deliveries in order.
+    let delivery_queue_index = IndexModel::builder()
+        .keys(doc! { "status": 1, "next_attempt_at": 1 })
+        .build();
+    db.collection::<Delivery>("webhook_deliveries").create_index(delivery_queue_index, None).await
+        .expect("Failed to create webhook delivery index");
+
+    let user_sessions_index = IndexModel::builder()
+        .keys(doc! { "user_id": 1 })
+        .build();
+    db.collection::<UserSession>("user_sessions").create_index(user_sessions_index, None).await
+        .expect("Failed to create user session index");
+
+    // Sessions past their absolute lifetime are dead anyway; let MongoDB drop them.
+    let session_expiry_index = IndexModel::builder()
+        .keys(doc! { "expires_at": 1 })
+        .options(IndexOptions::builder().expire_after(std::time::Duration::ZERO).build())
+        .build();
+    db.collection::<UserSession>("user_sessions").create_index(session_expiry_index, None).await
+        .expect("Failed to create user session expiry index");
+
+    // Usernames are unique regardless of case; see `profiles::username_collation`.
+    let username_index = IndexModel::builder()
+        .keys(doc! { "username": 1 })
+        .options(IndexOptions::builder().unique(true).collation(username_collation()).build())
+        .build();
+    db.collection::<User>("users").create_index(username_index, None).await
+        .expect("Failed to create username index");
+
+    // Sparse, as accounts from before `usernames` may lack a skeleton until
+    // `usernames::backfill_skeletons` has run, or for good if theirs clashes.
+    let username_skeleton_index = IndexModel::builder()
+        .keys(doc! { "username_skeleton": 1 })
+        .options(IndexOptions::builder().unique(true).sparse(true).build())
+        .build();
+    db.collection::<User>("users").create_index(username_skeleton_index, None).await
+        .expect("Failed to create username skeleton index");
+}
@@ -0,0 +1,170 @@
+use futures::TryStreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::FindOneOptions;
+use mongodb::Database;
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{GeneralSecurityProfile, MixedScript};
+
+use crate::auth::User;
+use crate::error::WebauthnError;
+use crate::profiles::is_duplicate_key;
+use crate::startup::AppState;
+
+const MIN_CHARS: usize = 3;
+const MAX_CHARS: usize = 32;
+const SEPARATORS: &[char] = &['_', '-', '.'];
+
+/// Names that could pass for the service itself or clash with routes and
+/// placeholders. Compared by skeleton, so look-alikes are caught too.
+const RESERVED: &[&str] = &[
+    "admin", "administrator", "api", "root", "system", "support", "help", "moderator", "mod",
+    "staff", "security", "official", "me", "null", "undefined", "deleted", "anonymous",
+    "login", "logout", "register", "recover", "account", "settings",
+];
+
+/// The form a username is stored and looked up in: NFKC, case-folded and
+/// trimmed. Folding can leave text unnormalized, hence the second pass.
+pub fn canonicalize(username: &str) -> String {
+    let folded = caseless::default_case_fold_str(&username.nfkc().collect::<String>());
+    folded.nfkc().collect::<String>().trim().to_string()
+}
+
+/// UTS #39 skeleton of a canonical username. Two names with the same
+/// skeleton are confusable.
+pub fn skeleton(username: &str) -> String {
+    unicode_security::skeleton(username).collect()
+}
+
+/// Canonicalizes a username chosen at registration or in a rename and
+/// checks it against the policy. Returns the canonical form.
+pub fn validate(username: &str) -> Result<String, WebauthnError> {
+    let username = canonicalize(username);
+    let length = username.chars().count();
+    if !(MIN_CHARS..=MAX_CHARS).contains(&length) {
+        return Err(WebauthnError::InvalidInput(format!("Username must be between {} and {} characters long", MIN_CHARS, MAX_CHARS)));
+    }
+    if !username.chars().all(|c| SEPARATORS.contains(&c) || (c.is_alphanumeric() && c.identifier_allowed())) {
+        return Err(WebauthnError::InvalidInput("Username can only contain letters, digits, '_', '-' and '.'".into()));
+    }
+    if username.starts_with(SEPARATORS) || username.ends_with(SEPARATORS) {
+        return Err(WebauthnError::InvalidInput("Username must start and end with a letter or digit".into()));
+    }
+    if !username.as_str().is_single_script() {
+        return Err(WebauthnError::InvalidInput("Username cannot mix letters from different alphabets".into()));
+    }
+    let username_skeleton = skeleton(&username);
+    if RESERVED.iter().any(|reserved| skeleton(reserved) == username_skeleton) {
+        return Err(WebauthnError::InvalidInput("This username is reserved".into()));
+    }
+    Ok(username)
+}
+
+/// Fails when another account's username looks like `username`. `except`
+/// is the account being renamed, which may keep resembling itself.
+pub async fn ensure_not_confusable(app_state: &AppState, username: &str, except: Option<&ObjectId>) -> Result<(), WebauthnError> {
+    let mut filter = doc! { "username_skeleton": skeleton(username) };
+    if let Some(user_id) = except {
+        filter.insert("_id", doc! { "$ne": user_id });
+    }
+    let options = FindOneOptions::builder().projection(doc! { "username": 1 }).build();
+    let existing = app_state.db.collection::<Document>("users").find_one(filter, options).await
+        .map_err(|e| { error!("Database error during user search: {:?}", e); WebauthnError::DatabaseError })?;
+    if let Some(existing) = existing {
+        info!("Username '{}' is confusable with '{}'", username, existing.get_str("username").unwrap_or_default());
+        return Err(WebauthnError::InvalidInput("Username is too similar to an existing username".into()));
+    }
+    Ok(())
+}
+
+/// Gives accounts from before `usernames` their skeleton, which
+/// `ensure_not_confusable` relies on. Run at startup, before requests are
+/// served. An account whose skeleton is already taken by a look-alike is
+/// left without one and logged: both names predate the check, and neither
+/// can be renamed automatically.
+pub async fn backfill_skeletons(db: &Database) {
+    let users = db.collection::<User>("users");
+    let mut cursor = users.find(doc! { "username_skeleton": { "$exists": false } }, None).await
+        .expect("Failed to fetch users without a username skeleton");
+    let mut filled = 0;
+    while let Some(user) = cursor.try_next().await.expect("Failed to collect users without a username skeleton") {
+        let Some(user_id) = user.id else { continue };
+        let username_skeleton = skeleton(&canonicalize(&user.username));
+        match users.update_one(doc! { "_id": &user_id }, doc! { "$set": { "username_skeleton": &username_skeleton } }, None).await {
+            Ok(_) => filled += 1,
+            Err(e) if is_duplicate_key(&e) => warn!("Username '{}' of user {} is confusable with another account's", user.username, user_id),
+            Err(e) => panic!("Failed to backfill username skeleton of user {}: {:?}", user_id, e),
+        }
+    }
+    if filled > 0 {
+        info!("Backfilled the username skeleton of {} users", filled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(username: &str) -> bool {
+        validate(username).is_err()
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        assert!(rejected("ab"));
+        assert_eq!(validate("abc").unwrap(), "abc");
+        assert!(validate(&"a".repeat(MAX_CHARS)).is_ok());
+        assert!(rejected(&"a".repeat(MAX_CHARS + 1)));
+        // Three letters, six bytes.
+        assert!(validate("жук").is_ok());
+    }
+
+    #[test]
+    fn case_and_compatibility_forms_are_folded() {
+        assert_eq!(validate("  Alice ").unwrap(), "alice");
+        assert_eq!(canonicalize("ＡＬＩＣＥ"), "alice");
+        assert_eq!(canonicalize("Straße"), "strasse");
+        assert_eq!(canonicalize("ﬁona"), "fiona");
+    }
+
+    #[test]
+    fn mixed_scripts_are_rejected() {
+        // Latin with a Cyrillic о.
+        assert!(rejected("bоb"));
+        assert!(validate("боб").is_ok());
+        assert!(validate("bob_2").is_ok());
+    }
+
+    #[test]
+    fn separators_and_symbols_are_limited() {
+        assert!(validate("a.b-c_d").is_ok());
+        assert!(rejected("_alice"));
+        assert!(rejected("alice."));
+        assert!(rejected("al ice"));
+        assert!(rejected("alice!"));
+    }
+
+    fn reason(username: &str) -> String {
+        match validate(username) {
+            Err(WebauthnError::InvalidInput(reason)) => reason,
+            other => panic!("{} was not rejected: {:?}", username, other),
+        }
+    }
+
+    #[test]
+    fn reserved_names_are_matched_by_skeleton() {
+        assert_eq!(reason("Admin"), "This username is reserved");
+        // "аdmin" with a Cyrillic а is caught as mixed-script before the
+        // reserved check, but shares the skeleton all the same.
+        assert_eq!(skeleton(&canonicalize("аdmin")), skeleton("admin"));
+        assert!(rejected("аdmin"));
+        // All Cyrillic, so only the skeleton gives "арі" away as "api".
+        assert_eq!(reason("арі"), "This username is reserved");
+    }
+
+    #[test]
+    fn look_alikes_share_a_skeleton() {
+        assert_eq!(skeleton(&canonicalize("paypal")), skeleton(&canonicalize("pаypаl")));
+        assert_eq!(skeleton("rn"), skeleton("m"));
+        assert_ne!(skeleton("alice"), skeleton("alicia"));
+    }
+}
@@ -0,0 +1,722 @@
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::Extension,
+    http::StatusCode,
+    response::IntoResponse,
+    Router, routing::{delete, get},
+};
+use futures::TryStreamExt;
+use hmac::{Hmac, Mac};
+use mongodb::bson::{doc, oid::ObjectId, to_bson, DateTime};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tower_sessions::Session;
+
+use crate::auth::is_authenticated;
+use crate::error::{FieldError, WebauthnError};
+use crate::extract::{parse_id, Json, Path};
+use crate::permissions::{authorize, Caller, PollAction};
+use crate::polls::Poll;
+use crate::startup::AppState;
+
+const SIGNATURE_HEADER: &str = "x-webhook-signature";
+const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+const EVENT_HEADER: &str = "x-webhook-event";
+const DELIVERY_HEADER: &str = "x-webhook-delivery";
+
+const WORKER_POLL_SECS: u64 = 2;
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+/// How long a claimed delivery stays invisible to other workers; if the
+/// worker dies mid-request it is retried after this.
+const DELIVERY_LEASE_SECS: i64 = 60;
+const MAX_ATTEMPTS: i32 = 8;
+const BACKOFF_BASE_SECS: i64 = 30;
+const BACKOFF_MAX_SECS: i64 = 6 * 60 * 60;
+const DELIVERY_LOG_LIMIT: i64 = 50;
+const MAX_WEBHOOKS_PER_USER: u64 = 20;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebhookEvent {
+    #[serde(rename = "poll.created")]
+    PollCreated,
+    #[serde(rename = "vote.cast")]
+    VoteCast,
+    #[serde(rename = "poll.closed")]
+    PollClosed,
+    #[serde(rename = "poll.reset")]
+    PollReset,
+}
+
+/// A subscription. Without `poll_id` it covers every poll the owner creates.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Webhook {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub owner_id: ObjectId,
+    pub url: String,
+    /// HMAC key for the signature header. Only shown when the webhook is created.
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub poll_id: Option<ObjectId>,
+    pub created_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// One try at delivering a payload.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeliveryAttempt {
+    pub at: DateTime,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+/// A queued payload for one webhook, with its attempt log.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Delivery {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub webhook_id: ObjectId,
+    pub event: WebhookEvent,
+    /// The exact body that is signed and sent.
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub next_attempt_at: DateTime,
+    pub attempts: Vec<DeliveryAttempt>,
+    pub created_at: DateTime,
+}
+
+impl Delivery {
+    /// Appends `attempt` to the log and works out what happens next: done
+    /// on a 2xx, given up after `MAX_ATTEMPTS`, otherwise retried after
+    /// `backoff`.
+    pub fn record(&mut self, attempt: DeliveryAttempt) {
+        let succeeded = attempt.status_code.is_some_and(|code| (200..300).contains(&code));
+        let at = attempt.at;
+        self.attempts.push(attempt);
+        let attempts = self.attempts.len();
+        (self.status, self.next_attempt_at) = if succeeded {
+            (DeliveryStatus::Delivered, at)
+        } else if attempts as i32 >= MAX_ATTEMPTS {
+            warn!("Webhook delivery {:?} failed after {} attempts", self.id, attempts);
+            (DeliveryStatus::Failed, at)
+        } else {
+            (DeliveryStatus::Pending, DateTime::from_millis(at.timestamp_millis() + backoff(attempts).as_millis() as i64))
+        };
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    #[serde(default)]
+    pub poll_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub poll_id: Option<String>,
+    pub created_at: String,
+    /// Only present in the response to creating the webhook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(webhook: Webhook) -> Self {
+        WebhookResponse {
+            id: webhook.id.map(|id| id.to_string()).unwrap_or_default(),
+            url: webhook.url,
+            events: webhook.events,
+            poll_id: webhook.poll_id.map(|id| id.to_string()),
+            created_at: webhook.created_at.to_string(),
+            secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryAttemptResponse {
+    pub at: String,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryResponse {
+    pub id: String,
+    pub event: WebhookEvent,
+    pub status: DeliveryStatus,
+    pub created_at: String,
+    pub next_attempt_at: Option<String>,
+    pub attempts: Vec<DeliveryAttemptResponse>,
+}
+
+impl From<Delivery> for DeliveryResponse {
+    fn from(delivery: Delivery) -> Self {
+        DeliveryResponse {
+            id: delivery.id.map(|id| id.to_string()).unwrap_or_default(),
+            event: delivery.event,
+            status: delivery.status,
+            created_at: delivery.created_at.to_string(),
+            next_attempt_at: (delivery.status == DeliveryStatus::Pending).then(|| delivery.next_attempt_at.to_string()),
+            attempts: delivery.attempts.into_iter().map(|attempt| DeliveryAttemptResponse {
+                at: attempt.at.to_string(),
+                status_code: attempt.status_code,
+                error: attempt.error,
+                duration_ms: attempt.duration_ms,
+            }).collect(),
+        }
+    }
+}
+
+/// Whether webhooks may be sent to `ip`. Loopback, private, link-local
+/// (which includes cloud metadata endpoints), unique-local and other
+/// special-purpose ranges are refused, so that a webhook can't be used to
+/// reach services inside our network.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified()
+                || ip.is_broadcast() || ip.is_documentation() || ip.is_multicast()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast()
+                // Unique local, fc00::/7, and link-local, fe80::/10.
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolves webhook hosts for the delivery client, dropping addresses that
+/// aren't public. Checking here rather than only when the webhook is
+/// created means a name can't be re-pointed at an internal address later:
+/// the address checked is the address connected to.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// The HTTP client deliveries go out on. Redirects are not followed, since
+/// they could lead anywhere.
+pub fn delivery_client(allow_private: bool) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+        .redirect(reqwest::redirect::Policy::none());
+    let builder = if allow_private { builder } else { builder.dns_resolver(Arc::new(PublicResolver)) };
+    builder.build().expect("Failed to build webhook HTTP client")
+}
+
+/// `sha256=<hex>` over `"<timestamp>.<body>"`, so receivers can reject
+/// replays of old payloads.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Delay before retrying after `attempts` failed tries: 30s, 1m, 2m, ...
+/// capped at six hours.
+fn backoff(attempts: usize) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(20) as u32;
+    Duration::from_secs(BACKOFF_BASE_SECS.saturating_mul(1 << exponent).min(BACKOFF_MAX_SECS) as u64)
+}
+
+/// Queues `event` for every webhook subscribed to the poll, either directly
+/// or through its creator, whose owner may still manage the poll's
+/// webhooks. Failures are logged: the event has already
+/// happened and the caller's request shouldn't fail because of a webhook.
+pub async fn dispatch(app_state: &AppState, event: WebhookEvent, poll_id: &ObjectId, poll: &Poll, data: serde_json::Value) {
+    if let Err(e) = enqueue(app_state, event, poll_id, poll, data).await {
+        error!("Failed to queue {:?} webhooks for poll {}: {:?}", event, poll_id, e);
+    }
+}
+
+async fn enqueue(app_state: &AppState, event: WebhookEvent, poll_id: &ObjectId, poll: &Poll, data: serde_json::Value) -> Result<(), mongodb::error::Error> {
+    let event_name = to_bson(&event)?;
+    let filter = doc! {
+        "events": event_name,
+        "$or": [{ "poll_id": poll_id }, { "poll_id": null, "owner_id": &poll.creator_id }],
+    };
+    let webhooks: Vec<Webhook> = app_state.db.collection::<Webhook>("webhooks").find(filter, None).await?.try_collect().await?;
+
+    // Roles change after a webhook is added; someone who has since lost
+    // theirs, or left the poll's organization, hears nothing more.
+    let mut webhook_ids = Vec::new();
+    for webhook in webhooks {
+        match Caller::for_poll(app_state, poll, webhook.owner_id).await {
+            Ok(caller) if authorize(poll, &caller, PollAction::ManageWebhooks).is_ok() => webhook_ids.extend(webhook.id),
+            Ok(_) => {}
+            Err(e) => error!("Failed to check the owner of webhook {:?}: {:?}", webhook.id, e),
+        }
+    }
+    if webhook_ids.is_empty() {
+        return Ok(());
+    }
+
+    let now = DateTime::now();
+    let payload = serde_json::json!({
+        "event": event,
+        "occurred_at": now.to_string(),
+        "poll": {
+            "id": poll_id.to_hex(),
+            "title": poll.title,
+            "creator_id": poll.creator_id.to_hex(),
+            "org_id": poll.org_id.map(|id| id.to_hex()),
+        },
+        "data": data,
+    }).to_string();
+    let deliveries = webhook_ids.into_iter().map(|webhook_id| Delivery {
+        id: None,
+        webhook_id,
+        event,
+        payload: payload.clone(),
+        status: DeliveryStatus::Pending,
+        next_attempt_at: now,
+        attempts: Vec::new(),
+        created_at: now,
+    });
+    app_state.db.collection::<Delivery>("webhook_deliveries").insert_many(deliveries, None).await?;
+    Ok(())
+}
+
+/// Background task that sends queued deliveries, one at a time, retrying
+/// failures with exponential backoff until `MAX_ATTEMPTS`.
+pub async fn run_delivery_worker(app_state: AppState) {
+    let allow_private = app_state.config.webhook_allow_private_targets;
+    let client = delivery_client(allow_private);
+    let mut interval = tokio::time::interval(Duration::from_secs(WORKER_POLL_SECS));
+    loop {
+        interval.tick().await;
+        loop {
+            match claim_next(&app_state).await {
+                Ok(Some(delivery)) => deliver(&app_state, &client, allow_private, delivery).await,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to claim webhook delivery: {:?}", e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+async fn claim_next(app_state: &AppState) -> Result<Option<Delivery>, mongodb::error::Error> {
+    let now = DateTime::now();
+    let lease_until = DateTime::from_millis(now.timestamp_millis() + DELIVERY_LEASE_SECS * 1000);
+    let options = FindOneAndUpdateOptions::builder()
+        .sort(doc! { "next_attempt_at": 1 })
+        .return_document(ReturnDocument::Before)
+        .build();
+    app_state.db.collection::<Delivery>("webhook_deliveries").find_one_and_update(
+        doc! { "status": "pending", "next_attempt_at": { "$lte": now } },
+        doc! { "$set": { "next_attempt_at": lease_until } },
+        options,
+    ).await
+}
+
+async fn deliver(app_state: &AppState, client: &reqwest::Client, allow_private: bool, mut delivery: Delivery) {
+    let Some(delivery_id) = delivery.id else { return };
+    let deliveries = app_state.db.collection::<Delivery>("webhook_deliveries");
+    let webhook = match app_state.db.collection::<Webhook>("webhooks").find_one(doc! { "_id": &delivery.webhook_id }, None).await {
+        Ok(Some(webhook)) => webhook,
+        Ok(None) => {
+            // The webhook was deleted while this was queued.
+            let _ = deliveries.update_one(doc! { "_id": &delivery_id }, doc! { "$set": { "status": "failed" } }, None).await;
+            return;
+        }
+        Err(e) => {
+            error!("Failed to fetch webhook {}: {:?}", delivery.webhook_id, e);
+            return;
+        }
+    };
+
+    let attempt = send_attempt(client, allow_private, &webhook, &delivery).await;
+    delivery.record(attempt);
+
+    let last = delivery.attempts.last().map(to_bson);
+    let (Ok(status), Some(Ok(attempt))) = (to_bson(&delivery.status), last) else {
+        error!("Failed to serialise webhook delivery {}", delivery_id);
+        return;
+    };
+    let update = doc! {
+        "$set": { "status": status, "next_attempt_at": delivery.next_attempt_at },
+        "$push": { "attempts": attempt },
+    };
+    if let Err(e) = deliveries.update_one(doc! { "_id": &delivery_id }, update, None).await {
+        error!("Failed to record webhook delivery {}: {:?}", delivery_id, e);
+    }
+}
+
+/// Makes one signed POST of the delivery's payload. IP literals skip DNS,
+/// so they are checked here; hostnames are checked by `PublicResolver`.
+async fn send_attempt(client: &reqwest::Client, allow_private: bool, webhook: &Webhook, delivery: &Delivery) -> DeliveryAttempt {
+    let mut attempt = DeliveryAttempt { at: DateTime::now(), status_code: None, error: None, duration_ms: 0 };
+    if !allow_private {
+        if let Err(message) = check_literal_host(&webhook.url) {
+            attempt.error = Some(message);
+            return attempt;
+        }
+    }
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let event = to_bson(&delivery.event).ok().and_then(|event| event.as_str().map(str::to_string)).unwrap_or_default();
+    let started = Instant::now();
+    let result = client.post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, event)
+        .header(DELIVERY_HEADER, delivery.id.map(|id| id.to_hex()).unwrap_or_default())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(&webhook.secret, timestamp, &delivery.payload))
+        .body(delivery.payload.clone())
+        .send().await;
+
+    attempt.at = DateTime::now();
+    attempt.duration_ms = started.elapsed().as_millis() as i64;
+    match result {
+        Ok(response) => attempt.status_code = Some(response.status().as_u16() as i32),
+        Err(e) => attempt.error = Some(e.to_string()),
+    }
+    attempt
+}
+
+fn check_literal_host(url: &str) -> Result<(), String> {
+    let url = url::Url::parse(url).map_err(|_| "URL is not valid".to_string())?;
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+        _ => return Ok(()),
+    };
+    if is_public_ip(ip) { Ok(()) } else { Err("URL must point to a public address".into()) }
+}
+
+/// Checks a webhook URL when it is created. Every address the host
+/// resolves to must be public; delivery checks again, as DNS can change.
+async fn validate_url(url: &str, allow_private: bool) -> Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|_| "URL is not valid".to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("URL must be http or https".into());
+    }
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return Err("URL must have a host".into());
+    };
+    if allow_private {
+        return Ok(());
+    }
+    check_literal_host(url)?;
+    // `host_str` keeps the brackets around IPv6 literals.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await
+        .map_err(|_| "URL host does not resolve".to_string())?
+        .collect();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public_ip(addr.ip())) {
+        return Err("URL must point to a public address".into());
+    }
+    Ok(())
+}
+
+async fn owned_webhook(app_state: &AppState, params: &HashMap<String, String>, user_id: &ObjectId) -> Result<Webhook, WebauthnError> {
+    let webhook_id = parse_id(params, "webhookId", "webhook")?;
+    app_state.db.collection::<Webhook>("webhooks").find_one(doc! { "_id": &webhook_id, "owner_id": user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch webhook: {:?}", e); WebauthnError::DatabaseError })?
+        .ok_or(WebauthnError::WebhookNotFound)
+}
+
+pub async fn list_webhooks(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let mut cursor = app_state.db.collection::<Webhook>("webhooks").find(doc! { "owner_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to fetch webhooks: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut webhooks = Vec::new();
+    while let Some(webhook) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect webhooks: {:?}", e); WebauthnError::DatabaseError })? {
+        webhooks.push(WebhookResponse::from(webhook));
+    }
+    Ok(Json(webhooks))
+}
+
+/// Subscribes the caller to events. A webhook for a single poll needs the
+/// owner or editor role on it; one without a poll only ever sees polls the
+/// caller created.
+pub async fn create_webhook(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+
+    let mut field_errors = Vec::new();
+    if let Err(message) = validate_url(&req.url, app_state.config.webhook_allow_private_targets).await {
+        field_errors.push(FieldError::new("url", message));
+    }
+    if req.events.is_empty() {
+        field_errors.push(FieldError::new("events", "Choose at least one event"));
+    }
+    if !field_errors.is_empty() {
+        return Err(WebauthnError::InvalidFields { message: "Webhook is invalid".into(), fields: field_errors });
+    }
+
+    let poll_id = match &req.poll_id {
+        Some(poll_id) => {
+            let poll_id = ObjectId::parse_str(poll_id).map_err(|_| WebauthnError::InvalidInput("Invalid poll ID".into()))?;
+            let poll = app_state.db.collection::<Poll>("polls").find_one(doc! { "_id": &poll_id }, None).await
+                .map_err(|e| { error!("Failed to fetch poll: {:?}", e); WebauthnError::DatabaseError })?
+                .ok_or(WebauthnError::PollNotFound)?;
+            let caller = Caller::for_poll(&app_state, &poll, user_id).await?;
+            authorize(&poll, &caller, PollAction::ManageWebhooks)?;
+            Some(poll_id)
+        }
+        None => None,
+    };
+
+    let webhook_collection = app_state.db.collection::<Webhook>("webhooks");
+    let existing = webhook_collection.count_documents(doc! { "owner_id": &user_id }, None).await
+        .map_err(|e| { error!("Failed to count webhooks: {:?}", e); WebauthnError::DatabaseError })?;
+    if existing >= MAX_WEBHOOKS_PER_USER {
+        return Err(WebauthnError::Conflict(format!("You can have at most {} webhooks", MAX_WEBHOOKS_PER_USER)));
+    }
+
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let mut seen = HashSet::new();
+    let mut events = req.events;
+    events.retain(|event| seen.insert(*event));
+    let mut webhook = Webhook {
+        id: None,
+        owner_id: user_id,
+        url: req.url,
+        secret: hex::encode(secret),
+        events,
+        poll_id,
+        created_at: DateTime::now(),
+    };
+    let result = webhook_collection.insert_one(&webhook, None).await
+        .map_err(|e| { error!("Failed to insert webhook: {:?}", e); WebauthnError::DatabaseError })?;
+    webhook.id = result.inserted_id.as_object_id();
+
+    info!("Webhook {:?} created by user {}", webhook.id, user_id);
+    let secret = webhook.secret.clone();
+    Ok((StatusCode::CREATED, Json(WebhookResponse { secret: Some(secret), ..WebhookResponse::from(webhook) })))
+}
+
+pub async fn delete_webhook(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let webhook = owned_webhook(&app_state, &params, &user_id).await?;
+
+    app_state.db.collection::<Webhook>("webhooks").delete_one(doc! { "_id": &webhook.id }, None).await
+        .map_err(|e| { error!("Failed to delete webhook: {:?}", e); WebauthnError::DatabaseError })?;
+    app_state.db.collection::<Delivery>("webhook_deliveries").delete_many(doc! { "webhook_id": &webhook.id }, None).await
+        .map_err(|e| { error!("Failed to delete webhook deliveries: {:?}", e); WebauthnError::DatabaseError })?;
+    Ok(StatusCode::OK)
+}
+
+/// The most recent deliveries for a webhook, each with every attempt made.
+pub async fn list_deliveries(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Path(params): Path<HashMap<String, String>>,
+) -> Result<impl IntoResponse, WebauthnError> {
+    let user_id = is_authenticated(&app_state, &session).await?;
+    let webhook = owned_webhook(&app_state, &params, &user_id).await?;
+
+    let options = FindOptions::builder().sort(doc! { "created_at": -1 }).limit(DELIVERY_LOG_LIMIT).build();
+    let mut cursor = app_state.db.collection::<Delivery>("webhook_deliveries")
+        .find(doc! { "webhook_id": &webhook.id }, options).await
+        .map_err(|e| { error!("Failed to fetch webhook deliveries: {:?}", e); WebauthnError::DatabaseError })?;
+
+    let mut deliveries = Vec::new();
+    while let Some(delivery) = cursor.try_next().await
+        .map_err(|e| { error!("Failed to collect webhook deliveries: {:?}", e); WebauthnError::DatabaseError })? {
+        deliveries.push(DeliveryResponse::from(delivery));
+    }
+    Ok(Json(deliveries))
+}
+
+pub fn routes() -> Router {
+    Router::new()
+        .route("/api/webhooks", get(list_webhooks).post(create_webhook))
+        .route("/api/webhooks/:webhookId", delete(delete_webhook))
+        .route("/api/webhooks/:webhookId/deliveries", get(list_deliveries))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use axum::{http::HeaderMap, routing::post};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// What the test receiver saw: the headers and body of each request.
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    /// Starts a receiver on a local port that fails the first `failures`
+    /// requests with a 500, then accepts. Returns its URL.
+    async fn receiver(failures: usize, received: Received) -> String {
+        let app = Router::new().route("/hook", post(move |headers: HeaderMap, body: String| async move {
+            let mut received = received.lock().unwrap();
+            received.push((headers, body));
+            if received.len() <= failures { StatusCode::INTERNAL_SERVER_ERROR } else { StatusCode::OK }
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/hook", addr)
+    }
+
+    fn webhook(url: String) -> Webhook {
+        Webhook {
+            id: Some(ObjectId::new()),
+            owner_id: ObjectId::new(),
+            url,
+            secret: "test-secret".into(),
+            events: vec![WebhookEvent::VoteCast],
+            poll_id: None,
+            created_at: DateTime::now(),
+        }
+    }
+
+    fn delivery(webhook: &Webhook) -> Delivery {
+        Delivery {
+            id: Some(ObjectId::new()),
+            webhook_id: webhook.id.unwrap(),
+            event: WebhookEvent::VoteCast,
+            payload: r#"{"event":"vote.cast","data":{"option":"a"}}"#.into(),
+            status: DeliveryStatus::Pending,
+            next_attempt_at: DateTime::now(),
+            attempts: Vec::new(),
+            created_at: DateTime::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn signature_matches_body() {
+        let received = Received::default();
+        let webhook = webhook(receiver(0, received.clone()).await);
+        let delivery = delivery(&webhook);
+
+        let attempt = send_attempt(&delivery_client(true), true, &webhook, &delivery).await;
+        assert_eq!(attempt.status_code, Some(200));
+
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[0];
+        assert_eq!(body, &delivery.payload);
+        let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(headers[SIGNATURE_HEADER].to_str().unwrap(), sign(&webhook.secret, timestamp, body));
+        assert_eq!(headers[EVENT_HEADER], "vote.cast");
+        assert_eq!(headers[DELIVERY_HEADER].to_str().unwrap(), delivery.id.unwrap().to_hex());
+    }
+
+    #[tokio::test]
+    async fn failures_are_retried_with_growing_backoff_and_logged() {
+        let received = Received::default();
+        let webhook = webhook(receiver(2, received.clone()).await);
+        let mut delivery = delivery(&webhook);
+        let client = delivery_client(true);
+
+        let mut delays = Vec::new();
+        while delivery.status == DeliveryStatus::Pending {
+            let attempt = send_attempt(&client, true, &webhook, &delivery).await;
+            let at = attempt.at;
+            delivery.record(attempt);
+            delays.push(delivery.next_attempt_at.timestamp_millis() - at.timestamp_millis());
+        }
+
+        assert_eq!(delivery.status, DeliveryStatus::Delivered);
+        assert_eq!(received.lock().unwrap().len(), 3);
+        let codes: Vec<_> = delivery.attempts.iter().map(|attempt| attempt.status_code).collect();
+        assert_eq!(codes, vec![Some(500), Some(500), Some(200)]);
+        assert_eq!(&delays[..2], &[30_000, 60_000]);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let webhook = webhook("http://example.com/hook".into());
+        let mut delivery = delivery(&webhook);
+        for _ in 0..MAX_ATTEMPTS {
+            delivery.record(DeliveryAttempt { at: DateTime::now(), status_code: None, error: Some("refused".into()), duration_ms: 0 });
+        }
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts.len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn only_public_addresses_are_allowed() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+                   "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} should be refused", ip);
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} should be allowed", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn private_targets_are_refused() {
+        assert!(validate_url("http://127.0.0.1:8080/hook", false).await.is_err());
+        assert!(validate_url("http://[::1]/hook", false).await.is_err());
+        assert!(validate_url("http://localhost/hook", false).await.is_err());
+        assert!(validate_url("ftp://example.com/hook", false).await.is_err());
+
+        // Delivery checks again, whatever was accepted at creation.
+        let received = Received::default();
+        let webhook = webhook(receiver(0, received.clone()).await);
+        let attempt = send_attempt(&delivery_client(false), false, &webhook, &delivery(&webhook)).await;
+        assert_eq!(attempt.status_code, None);
+        assert!(received.lock().unwrap().is_empty());
+
+        let webhook = Webhook { url: webhook.url.replace("127.0.0.1", "localhost"), ..webhook };
+        let attempt = send_attempt(&delivery_client(false), false, &webhook, &delivery(&webhook)).await;
+        assert_eq!(attempt.status_code, None);
+        assert!(received.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+'use client';
+
+import { useState, useEffect } from 'react';
+import { useRouter } from 'next/navigation';
+import Link from 'next/link';
+import { readApiError } from '../utils/apiError';
+
+interface SecurityEvent {
+    seq: number;
+    at: string;
+    action: string;
+    ip?: string;
+    user_agent?: string;
+}
+
+const actionLabels: Record<string, string> = {
+    user_registered: 'Account created',
+    login_succeeded: 'Signed in',
+    login_failed: 'Failed sign-in attempt',
+    logout: 'Signed out',
+    session_revoked: 'Session signed out',
+    all_sessions_revoked: 'Signed out everywhere',
+    reauthenticated: 'Identity confirmed with passkey',
+    reauthentication_failed: 'Failed identity confirmation',
+    recovery_codes_generated: 'New recovery codes generated',
+    recovery_code_used: 'Recovery code used',
+    passkey_recovered: 'Passkey replaced through recovery',
+    passkey_possibly_cloned: 'Passkey may have been copied',
+    username_changed: 'Username changed',
+    account_exported: 'Account data exported',
+    account_deleted: 'Account deleted',
+    user_suspended: 'Account suspended',
+    user_unsuspended: 'Account reinstated',
+    user_role_changed: 'Account role changed',
+    api_token_created: 'API token created',
+    api_token_revoked: 'API token revoked',
+};
+
+export default function SecurityActivity() {
+    const [events, setEvents] = useState<SecurityEvent[]>([]);
+    const [error, setError] = useState<string | null>(null);
+    const [isLoading, setIsLoading] = useState(true);
+    const router = useRouter();
+
+    useEffect(() => {
+        const fetchActivity = async () => {
+            try {
+                const res = await fetch('http://localhost:8080/api/auth/activity', {
+                    credentials: 'include',
+                });
+                if (res.status === 401) {
+                    router.push('/login');
+                    return;
+                }
+                if (!res.ok) throw await readApiError(res, 'Failed to load security activity');
+                setEvents(await res.json());
+            } catch (err) {
+                setError(err.message);
+            } finally {
+                setIsLoading(false);
+            }
+        };
+        fetchActivity();
+    }, [router]);
+
+    const container = {
+        maxWidth: '720px',
+        margin: '0 auto',
+        padding: '48px 24px',
+    };
+
+    const title = {
+        fontSize: '28px',
+        fontWeight: '700',
+        marginBottom: '24px',
+        color: '#1f2937',
+    };
+
+    const card = {
+        background: 'white',
+        borderRadius: '16px',
+        padding: '16px 20px',
+        marginBottom: '12px',
+        boxShadow: '0 4px 12px rgba(0, 0, 0, 0.05)',
+        border: '1px solid rgba(0, 0, 0, 0.05)',
+    };
+
+    const failedCard = {
+        ...card,
+        borderColor: 'rgba(236, 72, 153, 0.4)',
+    };
+
+    const meta = {
+        fontSize: '13px',
+        color: '#6b7280',
+        marginTop: '4px',
+    };
+
+    const backLink = {
+        color: '#8b5cf6',
+        fontWeight: '600',
+        textDecoration: 'none',
+    };
+
+    return (
+        <div style={container}>
+            <Link href="/" style={backLink}>← Back</Link>
+            <h1 style={title}>Recent security activity</h1>
+            {isLoading ? (
+                <p>Loading...</p>
+            ) : error ? (
+                <p style={{ color: '#ec4899' }}>{error}</p>
+            ) : events.length === 0 ? (
+                <p>No activity yet.</p>
+            ) : (
+                events.map(event => (
+                    <div key={event.seq} style={event.action === 'login_failed' ? failedCard : card}>
+                        <div style={{ fontWeight: '600' }}>{actionLabels[event.action] ?? event.action}</div>
+                        <div style={meta}>
+                            {new Date(event.at).toLocaleString()}
+                            {event.ip && ` · ${event.ip}`}
+                            {event.user_agent && ` · ${event.user_agent}`}
+                        </div>
+                    </div>
+                ))
+            )}
+        </div>
+    );
+}
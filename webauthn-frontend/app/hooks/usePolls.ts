@@ -1,5 +1,7 @@
 // webauthn-frontend/app/hooks/usePolls.ts
 import { useState, useEffect, useCallback } from 'react';
+import { readApiError } from '../utils/apiError';
+import { reauthenticate } from '../utils/auth';
 
 interface PollOption {
     id: string;
@@ -66,8 +68,7 @@ export function usePolls(options: UsePollsOptions = {}): UsePollsResult {
             });
 
             if (!response.ok) {
-                const errorText = await response.text();
-                throw new Error(errorText || 'Failed to fetch polls');
+                throw await readApiError(response, 'Failed to fetch polls');
             }
 
             const data = await response.json();
@@ -89,8 +90,7 @@ export function usePolls(options: UsePollsOptions = {}): UsePollsResult {
             });
 
             if (!response.ok) {
-                const errorText = await response.text();
-                throw new Error(errorText || 'Failed to close poll');
+                throw await readApiError(response, 'Failed to close poll');
             }
 
             // Update local state
@@ -111,14 +111,23 @@ export function usePolls(options: UsePollsOptions = {}): UsePollsResult {
     // Reset poll votes
     const resetPolls = async (pollId: string): Promise<boolean> => {
         try {
-            const response = await fetch(`${API_BASE_URL}/api/polls/${pollId}/reset`, {
+            const reset = () => fetch(`${API_BASE_URL}/api/polls/${pollId}/reset`, {
                 method: 'POST',
                 credentials: 'include',
             });
 
+            let response = await reset();
             if (!response.ok) {
-                const errorText = await response.text();
-                throw new Error(errorText || 'Failed to reset poll');
+                const apiError = await readApiError(response, 'Failed to reset poll');
+                if (apiError.code !== 'reauthentication_required') {
+                    throw apiError;
+                }
+                // Resetting needs a fresh passkey check; do it and try once more
+                await reauthenticate();
+                response = await reset();
+                if (!response.ok) {
+                    throw await readApiError(response, 'Failed to reset poll');
+                }
             }
 
             // Update local state
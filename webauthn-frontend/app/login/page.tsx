@@ -1,13 +1,15 @@
 'use client';
 
 import { useState, useEffect } from 'react';
-import { loginUser } from '../utils/auth';
+import { loginUser, recoverAccount } from '../utils/auth';
 import { useRouter } from 'next/navigation';
 import Link from 'next/link';
 
 export default function Login() {
     const [username, setUsername] = useState('');
     const [message, setMessage] = useState('');
+    const [recovering, setRecovering] = useState(false);
+    const [recoveryCode, setRecoveryCode] = useState('');
     const router = useRouter();
 
     const handleLogin = async () => {
@@ -21,6 +23,19 @@ export default function Login() {
         }
     };
 
+    // Replaces a lost passkey with a new one using a recovery code
+    const handleRecover = async () => {
+        try {
+            if (!username) throw new Error('Please enter a username');
+            if (!recoveryCode) throw new Error('Please enter a recovery code');
+            await recoverAccount(username, recoveryCode);
+            setMessage('New passkey registered! Redirecting...');
+            setTimeout(() => router.push('/'), 2000);
+        } catch (error) {
+            setMessage(`Error: ${error.message}`);
+        }
+    };
+
     // Animation keyframes
     useEffect(() => {
         const styleTag = document.createElement('style');
@@ -184,13 +199,22 @@ export default function Login() {
                         placeholder="Enter username"
                         style={inputField}
                     />
+                    {recovering && (
+                        <input
+                            type="text"
+                            value={recoveryCode}
+                            onChange={(e) => setRecoveryCode(e.target.value)}
+                            placeholder="Recovery code, e.g. ABCD-EFGH-JKMN"
+                            style={inputField}
+                        />
+                    )}
                     <button
-                        onClick={handleLogin}
+                        onClick={recovering ? handleRecover : handleLogin}
                         style={loginButton}
                         onMouseOver={(e) => (e.currentTarget.style.boxShadow = '0 6px 20px rgba(139, 92, 246, 0.5)')}
                         onMouseOut={(e) => (e.currentTarget.style.boxShadow = '0 4px 14px rgba(139, 92, 246, 0.3)')}
                     >
-                        Login
+                        {recovering ? 'Register a new passkey' : 'Login'}
                     </button>
                     {message && (
                         <p
@@ -202,6 +226,12 @@ export default function Login() {
                             {message}
                         </p>
                     )}
+                    <a
+                        onClick={() => { setRecovering(!recovering); setMessage(''); }}
+                        style={{ ...linkStyle, cursor: 'pointer' }}
+                    >
+                        {recovering ? 'Back to login' : 'Lost your passkey? Use a recovery code'}
+                    </a>
                     <Link href="/register" style={linkStyle}>
                         Don't have an account? Register here
                     </Link>
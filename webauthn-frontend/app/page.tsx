@@ -2,6 +2,7 @@
 
 import { useState, useEffect } from 'react';
 import Link from 'next/link';
+import { readApiError } from './utils/apiError';
 
 interface PollOption {
   id: string;
@@ -17,6 +18,7 @@ interface Poll {
   is_closed: boolean;
   creator_id: string;
   creator_username: string;
+  creator_display_name: string;
   created_at: string;
 }
 
@@ -74,7 +76,7 @@ export default function Home() {
           method: 'GET',
           credentials: 'include', // Sends cookies if present, but not required
         });
-        if (!res.ok) throw new Error(`Failed to fetch live polls: ${await res.text()}`);
+        if (!res.ok) throw await readApiError(res, 'Failed to fetch live polls');
         const data: Poll[] = await res.json();
         setPolls(data);
         setError(null);
@@ -530,7 +532,7 @@ export default function Home() {
 
                         <div style={creatorBadge}>
                           <div style={creatorAvatar}></div>
-                          <span style={creatorName}>@{poll.creator_username || 'Unknown'}</span>
+                          <span style={creatorName}>{poll.creator_display_name || poll.creator_username || 'Unknown'}</span>
                         </div>
 
                         <div style={{ margin: '1rem 0' }}>
@@ -4,6 +4,7 @@ import { useState, useEffect, useCallback } from 'react';
 import { useParams, useRouter } from 'next/navigation';
 import Link from 'next/link';
 import { useAuthStore } from '../../store';
+import { readApiError } from '../../utils/apiError';
 
 interface PollOption {
     id: string;
@@ -100,7 +101,7 @@ export default function PollPage() {
             });
 
             if (!res.ok) {
-                throw new Error(await res.text());
+                throw await readApiError(res, 'Failed to load poll');
             }
 
             const data = await res.json();
@@ -207,7 +208,7 @@ export default function PollPage() {
             });
 
             if (!res.ok) {
-                throw new Error(await res.text());
+                throw await readApiError(res, 'Failed to submit vote');
             }
 
             // Record the vote in localStorage
@@ -5,6 +5,7 @@ import { useRouter } from 'next/navigation';
 import Link from 'next/link';
 import { useAuthStore } from '../../store';
 import { checkAuthStatus } from '../../utils/auth';
+import { readApiError } from '../../utils/apiError';
 
 export default function NewPoll() {
     const [title, setTitle] = useState('');
@@ -140,8 +141,7 @@ export default function NewPoll() {
             });
 
             if (!res.ok) {
-                const errorText = await res.text();
-                throw new Error(errorText || 'Failed to create poll');
+                throw await readApiError(res, 'Failed to create poll');
             }
 
             const data = await res.json();
@@ -7,7 +7,9 @@ import Link from 'next/link';
 
 export default function Register() {
     const [username, setUsername] = useState('');
+    const [displayName, setDisplayName] = useState('');
     const [message, setMessage] = useState('');
+    const [recoveryCodes, setRecoveryCodes] = useState<string[]>([]);
     const router = useRouter();
 
     // Animation keyframes
@@ -31,9 +33,9 @@ export default function Register() {
     const handleRegister = async () => {
         try {
             if (!username) throw new Error('Please enter a username');
-            await registerUser(username);
-            setMessage('Successfully registered! Redirecting to login...');
-            setTimeout(() => router.push('/login'), 2000);
+            const codes = await registerUser(username, displayName.trim() || undefined);
+            setRecoveryCodes(codes);
+            setMessage('Successfully registered! Save your recovery codes before continuing.');
         } catch (error) {
             setMessage(`Error: ${error.message}`);
         }
@@ -161,6 +163,19 @@ export default function Register() {
         textAlign: 'center' as const,
     };
 
+    const codeList = {
+        display: 'grid',
+        gridTemplateColumns: '1fr 1fr',
+        gap: '0.5rem',
+        margin: '1.5rem 0',
+        padding: '1rem',
+        background: '#f3f4f6',
+        borderRadius: '0.75rem',
+        fontFamily: 'monospace',
+        fontSize: '0.95rem',
+        textAlign: 'center' as const,
+    };
+
     const footer = {
         padding: '16px',
         textAlign: 'center' as const,
@@ -191,6 +206,13 @@ export default function Register() {
                         placeholder="Enter username"
                         style={inputField}
                     />
+                    <input
+                        type="text"
+                        value={displayName}
+                        onChange={(e) => setDisplayName(e.target.value)}
+                        placeholder="Display name (optional)"
+                        style={inputField}
+                    />
                     <button
                         onClick={handleRegister}
                         style={registerButton}
@@ -209,6 +231,20 @@ export default function Register() {
                             {message}
                         </p>
                     )}
+                    {recoveryCodes.length > 0 && (
+                        <>
+                            <p style={{ ...messageText, color: '#374151' }}>
+                                Each code can be used once to get back into your account if you lose your passkey.
+                                They will not be shown again.
+                            </p>
+                            <div style={codeList}>
+                                {recoveryCodes.map((code) => <span key={code}>{code}</span>)}
+                            </div>
+                            <button onClick={() => router.push('/login')} style={registerButton}>
+                                I have saved my codes
+                            </button>
+                        </>
+                    )}
                     <Link href="/login" style={linkStyle}>
                         Already have an account? Login here
                     </Link>
@@ -0,0 +1,48 @@
+// webauthn-frontend/app/utils/apiError.ts
+
+export interface FieldError {
+    field: string;
+    message: string;
+}
+
+// Mirrors the backend's RFC 7807 problem+json body
+export interface Problem {
+    type: string;
+    title: string;
+    status: number;
+    detail: string;
+    code: string;
+    request_id?: string;
+    errors?: FieldError[];
+}
+
+export class ApiError extends Error {
+    public status: number;
+    public code?: string;
+    public requestId?: string;
+    public fields: FieldError[];
+
+    constructor(message: string, status: number, problem?: Problem) {
+        super(message);
+        this.name = 'ApiError';
+        this.status = status;
+        this.code = problem?.code;
+        this.requestId = problem?.request_id;
+        this.fields = problem?.errors ?? [];
+    }
+}
+
+// Reads an error response, preferring the problem+json detail over raw text
+export async function readApiError(response: Response, fallback: string): Promise<ApiError> {
+    const contentType = response.headers.get('content-type') ?? '';
+    if (contentType.includes('json')) {
+        try {
+            const problem = await response.json() as Problem;
+            return new ApiError(problem.detail || fallback, response.status, problem);
+        } catch {
+            return new ApiError(fallback, response.status);
+        }
+    }
+    const errorText = await response.text();
+    return new ApiError(errorText || fallback, response.status);
+}
@@ -1,16 +1,19 @@
 // webauthn-frontend/app/utils/auth.ts
 import { useAuthStore } from '../store';
+import { readApiError } from './apiError';
 
 const baseUrl = 'http://localhost:8080';
 
 // Improved error handling with typed errors
 export class AuthError extends Error {
     public status?: number;
+    public code?: string;
 
-    constructor(message: string, status?: number) {
+    constructor(message: string, status?: number, code?: string) {
         super(message);
         this.name = 'AuthError';
         this.status = status;
+        this.code = code;
     }
 }
 
@@ -42,8 +45,8 @@ export function isWebAuthnSupported(): boolean {
 // Helper for handling API responses
 async function handleApiResponse(response: Response): Promise<any> {
     if (!response.ok) {
-        const errorText = await response.text();
-        throw new AuthError(errorText || 'Authentication operation failed', response.status);
+        const apiError = await readApiError(response, 'Authentication operation failed');
+        throw new AuthError(apiError.message, apiError.status, apiError.code);
     }
 
     return response.headers.get('content-type')?.includes('application/json')
@@ -51,70 +54,165 @@ async function handleApiResponse(response: Response): Promise<any> {
         : {};
 }
 
-export async function registerUser(username: string): Promise<void> {
+// Error codes from *_finish endpoints when the ceremony itself is stale:
+// challenge_missing (never started, or replaced by a newer prompt, e.g. in
+// another tab), challenge_expired (answered too late) and
+// challenge_already_used (the same answer was sent twice). The first two
+// need a fresh start; the last usually means the first attempt went through
+export const CEREMONY_ERROR_CODES = ['challenge_missing', 'challenge_expired', 'challenge_already_used'];
+
+export function isCeremonyError(error: unknown): error is AuthError {
+    return error instanceof AuthError && CEREMONY_ERROR_CODES.includes(error.code ?? '');
+}
+
+// *_start responses carry a ceremony_id, which the finish call echoes back
+// so the backend can tell an expired, replaced or replayed prompt apart
+function ceremonyUrl(finishUrl: string, options: any): string {
+    return options.ceremony_id ? `${finishUrl}?ceremony=${encodeURIComponent(options.ceremony_id)}` : finishUrl;
+}
+
+// Creates a passkey for options from a *register_start endpoint and posts
+// the attestation to the matching *register_finish endpoint
+async function sendAttestation(options: any, finishUrl: string): Promise<Response> {
+    options.publicKey.challenge = base64ToUint8Array(options.publicKey.challenge);
+    options.publicKey.user.id = base64ToUint8Array(options.publicKey.user.id);
+
+    if (options.publicKey.excludeCredentials) {
+        options.publicKey.excludeCredentials.forEach((cred: any) => {
+            cred.id = base64ToUint8Array(cred.id);
+        });
+    }
+
+    const credential = await navigator.credentials.create({
+        publicKey: options.publicKey
+    }) as PublicKeyCredential;
+
+    if (!credential) {
+        throw new AuthError('Credential creation failed');
+    }
+
+    const response = credential.response as AuthenticatorAttestationResponse;
+    return fetch(ceremonyUrl(finishUrl, options), {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+            id: credential.id,
+            rawId: uint8ArrayToBase64(new Uint8Array(credential.rawId)),
+            type: credential.type,
+            response: {
+                attestationObject: uint8ArrayToBase64(new Uint8Array(response.attestationObject)),
+                clientDataJSON: uint8ArrayToBase64(new Uint8Array(response.clientDataJSON)),
+            },
+        }),
+        credentials: 'include',
+    });
+}
+
+// Registers a new account and returns its one-time recovery codes, which
+// are never shown again
+export async function registerUser(username: string, displayName?: string): Promise<string[]> {
     if (!isWebAuthnSupported()) {
         throw new AuthError('Your browser does not support passkeys. Please use a modern browser.');
     }
 
     try {
         // Step 1: Start registration
-        const startResponse = await fetch(`${baseUrl}/register_start/${encodeURIComponent(username)}`, {
+        const query = displayName ? `?display_name=${encodeURIComponent(displayName)}` : '';
+        const startResponse = await fetch(`${baseUrl}/register_start/${encodeURIComponent(username)}${query}`, {
             method: 'POST',
             credentials: 'include',
         });
 
         const options = await handleApiResponse(startResponse);
 
-        // Step 2: Prepare credential creation options
-        options.publicKey.challenge = base64ToUint8Array(options.publicKey.challenge);
-        options.publicKey.user.id = base64ToUint8Array(options.publicKey.user.id);
+        // Steps 2-4: Create the credential and send it back
+        const finishResponse = await sendAttestation(options, `${baseUrl}/register_finish`);
 
-        if (options.publicKey.excludeCredentials) {
-            options.publicKey.excludeCredentials.forEach((cred: any) => {
-                cred.id = base64ToUint8Array(cred.id);
-            });
+        const result = await handleApiResponse(finishResponse);
+        return result.recovery_codes ?? [];
+    } catch (error) {
+        // Re-throw with better error messaging
+        if (error instanceof AuthError) {
+            throw error;
         }
 
-        // Step 3: Create credential
-        const credential = await navigator.credentials.create({
-            publicKey: options.publicKey
-        }) as PublicKeyCredential;
+        throw new AuthError(`Registration failed: ${error.message}`);
+    }
+}
 
-        if (!credential) {
-            throw new AuthError('Credential creation failed');
-        }
+// Uses a recovery code to replace a lost passkey with a new one on this
+// device, which also logs the user in
+export async function recoverAccount(username: string, code: string): Promise<void> {
+    if (!isWebAuthnSupported()) {
+        throw new AuthError('Your browser does not support passkeys. Please use a modern browser.');
+    }
 
-        // Step 4: Finish registration
-        const finishResponse = await fetch(`${baseUrl}/register_finish`, {
+    try {
+        const recoverResponse = await fetch(`${baseUrl}/recover/${encodeURIComponent(username)}`, {
             method: 'POST',
             headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({
-                id: credential.id,
-                rawId: uint8ArrayToBase64(new Uint8Array(credential.rawId)),
-                type: credential.type,
-                response: {
-                    attestationObject: uint8ArrayToBase64(
-                        new Uint8Array((credential.response as AuthenticatorAttestationResponse).attestationObject)
-                    ),
-                    clientDataJSON: uint8ArrayToBase64(
-                        new Uint8Array((credential.response as AuthenticatorAttestationResponse).clientDataJSON)
-                    ),
-                },
-            }),
+            body: JSON.stringify({ code }),
             credentials: 'include',
         });
+        await handleApiResponse(recoverResponse);
 
+        const startResponse = await fetch(`${baseUrl}/recover_register_start`, {
+            method: 'POST',
+            credentials: 'include',
+        });
+        const options = await handleApiResponse(startResponse);
+
+        const finishResponse = await sendAttestation(options, `${baseUrl}/recover_register_finish`);
         await handleApiResponse(finishResponse);
+
+        useAuthStore.getState().setLoggedIn(true);
     } catch (error) {
-        // Re-throw with better error messaging
         if (error instanceof AuthError) {
             throw error;
         }
 
-        throw new AuthError(`Registration failed: ${error.message}`);
+        throw new AuthError(`Account recovery failed: ${error.message}`);
     }
 }
 
+// Signs a challenge from a *_start endpoint with a passkey and posts the
+// assertion to the matching *_finish endpoint
+async function sendAssertion(options: any, finishUrl: string): Promise<Response> {
+    options.publicKey.challenge = base64ToUint8Array(options.publicKey.challenge);
+
+    if (options.publicKey.allowCredentials) {
+        options.publicKey.allowCredentials.forEach((cred: any) => {
+            cred.id = base64ToUint8Array(cred.id);
+        });
+    }
+
+    const assertion = await navigator.credentials.get({
+        publicKey: options.publicKey
+    }) as PublicKeyCredential;
+
+    if (!assertion) {
+        throw new AuthError('Authentication failed');
+    }
+
+    const response = assertion.response as AuthenticatorAssertionResponse;
+    return fetch(ceremonyUrl(finishUrl, options), {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+            id: assertion.id,
+            rawId: uint8ArrayToBase64(new Uint8Array(assertion.rawId)),
+            type: assertion.type,
+            response: {
+                authenticatorData: uint8ArrayToBase64(new Uint8Array(response.authenticatorData)),
+                clientDataJSON: uint8ArrayToBase64(new Uint8Array(response.clientDataJSON)),
+                signature: uint8ArrayToBase64(new Uint8Array(response.signature)),
+                userHandle: response.userHandle ? uint8ArrayToBase64(new Uint8Array(response.userHandle)) : '',
+            },
+        }),
+        credentials: 'include',
+    });
+}
+
 export async function loginUser(username: string): Promise<void> {
     if (!isWebAuthnSupported()) {
         throw new AuthError('Your browser does not support passkeys. Please use a modern browser.');
@@ -129,61 +227,52 @@ export async function loginUser(username: string): Promise<void> {
 
         const options = await handleApiResponse(startResponse);
 
-        // Step 2: Prepare credential request options
-        options.publicKey.challenge = base64ToUint8Array(options.publicKey.challenge);
+        // Steps 2-4: Sign the challenge and send the assertion back
+        const finishResponse = await sendAssertion(options, `${baseUrl}/login_finish`);
 
-        if (options.publicKey.allowCredentials) {
-            options.publicKey.allowCredentials.forEach((cred: any) => {
-                cred.id = base64ToUint8Array(cred.id);
-            });
+        try {
+            await handleApiResponse(finishResponse);
+        } catch (error) {
+            // A repeated submit of a login that already succeeded
+            if (!(isCeremonyError(error) && error.code === 'challenge_already_used' && await checkAuthStatus())) {
+                throw error;
+            }
         }
 
-        // Step 3: Get credential
-        const assertion = await navigator.credentials.get({
-            publicKey: options.publicKey
-        }) as PublicKeyCredential;
-
-        if (!assertion) {
-            throw new AuthError('Authentication failed');
+        // Update auth state in Zustand store
+        useAuthStore.getState().setLoggedIn(true);
+    } catch (error) {
+        // Re-throw with better error messaging
+        if (error instanceof AuthError) {
+            throw error;
         }
 
-        // Step 4: Finish authentication
-        const finishResponse = await fetch(`${baseUrl}/login_finish`, {
+        throw new AuthError(`Login failed: ${error.message}`);
+    }
+}
+
+// Confirms the logged-in user with their passkey again. The backend asks for
+// this (code "reauthentication_required") before destructive actions
+export async function reauthenticate(): Promise<void> {
+    if (!isWebAuthnSupported()) {
+        throw new AuthError('Your browser does not support passkeys. Please use a modern browser.');
+    }
+
+    try {
+        const startResponse = await fetch(`${baseUrl}/api/auth/reauth_start`, {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({
-                id: assertion.id,
-                rawId: uint8ArrayToBase64(new Uint8Array(assertion.rawId)),
-                type: assertion.type,
-                response: {
-                    authenticatorData: uint8ArrayToBase64(
-                        new Uint8Array((assertion.response as AuthenticatorAssertionResponse).authenticatorData)
-                    ),
-                    clientDataJSON: uint8ArrayToBase64(
-                        new Uint8Array((assertion.response as AuthenticatorAssertionResponse).clientDataJSON)
-                    ),
-                    signature: uint8ArrayToBase64(
-                        new Uint8Array((assertion.response as AuthenticatorAssertionResponse).signature)
-                    ),
-                    userHandle: assertion.response && (assertion.response as AuthenticatorAssertionResponse).userHandle
-                        ? uint8ArrayToBase64(new Uint8Array((assertion.response as AuthenticatorAssertionResponse).userHandle!))
-                        : '',
-                },
-            }),
             credentials: 'include',
         });
+        const options = await handleApiResponse(startResponse);
 
+        const finishResponse = await sendAssertion(options, `${baseUrl}/api/auth/reauth_finish`);
         await handleApiResponse(finishResponse);
-
-        // Update auth state in Zustand store
-        useAuthStore.getState().setLoggedIn(true);
     } catch (error) {
-        // Re-throw with better error messaging
         if (error instanceof AuthError) {
             throw error;
         }
 
-        throw new AuthError(`Login failed: ${error.message}`);
+        throw new AuthError(`Confirming your identity failed: ${error.message}`);
     }
 }
 